@@ -0,0 +1,72 @@
+//! Gathers the fonts embedded by the `bundled-fonts` feature, along with their license.
+//! They are taken from the `fonts` directory if it has them all, or downloaded from the IPA.
+
+#[cfg(feature = "bundled-fonts")]
+mod bundled {
+    use std::env;
+    use std::error::Error;
+    use std::fs::{copy, rename, File};
+    use std::io;
+    use std::path::{Path, PathBuf};
+    use std::process::Command;
+
+    /// The archive of the IPAex fonts, published by the Information-technology Promotion Agency.
+    const ARCHIVE_URL: &str = "https://moji.or.jp/wp-content/ipafont/IPAexfont/IPAexfont00401.zip";
+    /// The directory of the archive the files are in.
+    const ARCHIVE_DIRECTORY: &str = "IPAexfont00401";
+    /// The files that are embedded, the mincho & gothic fonts followed by their license.
+    const FILES: [&str; 3] = [
+        "ipaexm.ttf",
+        "ipaexg.ttf",
+        "IPA_Font_License_Agreement_v1.0.txt",
+    ];
+
+    /// Puts the fonts & their license in the output directory, where the binary embeds them from.
+    pub(super) fn gather() -> Result<(), Box<dyn Error>> {
+        println!("cargo:rerun-if-changed=fonts");
+        let output = PathBuf::from(env::var_os("OUT_DIR").ok_or("OUT_DIR is not set")?);
+        let local = Path::new("fonts");
+        if FILES.iter().all(|file| local.join(file).is_file()) {
+            for file in FILES {
+                let _bytes = copy(local.join(file), output.join(file))?;
+            }
+            return Ok(());
+        }
+        let archive_path = output.join(format!("{ARCHIVE_DIRECTORY}.zip"));
+        if !archive_path.is_file() {
+            // Downloaded under another name first, so a failed download is not mistaken for one
+            let partial_path = archive_path.with_extension("part");
+            let status = Command::new("curl")
+                .args([
+                    "--fail",
+                    "--location",
+                    "--silent",
+                    "--show-error",
+                    "--output",
+                ])
+                .arg(&partial_path)
+                .arg(ARCHIVE_URL)
+                .status()?;
+            if !status.success() {
+                return Err(format!(
+                    "downloading {ARCHIVE_URL} failed; extract it into the fonts directory instead"
+                )
+                .into());
+            }
+            rename(partial_path, &archive_path)?;
+        }
+        let mut archive = zip::ZipArchive::new(File::open(&archive_path)?)?;
+        for file in FILES {
+            let mut entry = archive.by_name(&format!("{ARCHIVE_DIRECTORY}/{file}"))?;
+            let _bytes = io::copy(&mut entry, &mut File::create(output.join(file))?)?;
+        }
+        Ok(())
+    }
+}
+
+fn main() {
+    #[cfg(feature = "bundled-fonts")]
+    if let Err(error) = bundled::gather() {
+        panic!("Failed to gather the bundled fonts: {error}");
+    }
+}
@@ -1,10 +1,49 @@
 //! Contains the code for handling CLI arguments.
+use crate::cv::Conformance;
+use crate::style::core::Permission;
 use clap::Parser;
 use std::path::PathBuf;
 
+const KIBIBYTE: u64 = 1024;
+const MEBIBYTE: u64 = 1024 * 1024;
+
+/// Parses a file size such as `2MB`, `500KB` or a plain number of bytes.
+fn parse_file_size(size: &str) -> Result<u64, String> {
+    let upper = size.trim().to_ascii_uppercase();
+    let (number, unit) = if let Some(number) = upper.strip_suffix("MB") {
+        (number, MEBIBYTE)
+    } else if let Some(number) = upper.strip_suffix("KB") {
+        (number, KIBIBYTE)
+    } else {
+        (upper.strip_suffix('B').unwrap_or(&upper), 1)
+    };
+    number
+        .trim()
+        .parse::<u64>()
+        .map(|number| number * unit)
+        .map_err(|error| format!("Invalid file size {size}: {error}"))
+}
+
+/// Parses a resolution, which has to be a positive & finite number of dots per inch.
+fn parse_dpi(dpi: &str) -> Result<f64, String> {
+    let value = dpi
+        .trim()
+        .parse::<f64>()
+        .map_err(|error| format!("Invalid resolution {dpi}: {error}"))?;
+    if value.is_finite() && value > 0.0 {
+        Ok(value)
+    } else {
+        Err(format!("Invalid resolution {dpi}: it must be above 0"))
+    }
+}
+
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
 /// The standard arguments for the CLI.
+#[allow(
+    clippy::struct_excessive_bools,
+    reason = "the flags of the command line are switches"
+)]
 pub(crate) struct Args {
     /// Path to the input file in YAML format.
     #[arg(short, long, default_value = "data.yaml")]
@@ -17,6 +56,112 @@ pub(crate) struct Args {
     /// Path to output the final PDF file to.
     #[arg(short, long, default_value = "output.pdf")]
     pub(crate) output: PathBuf,
+
+    /// Turn emails & URLs in values into clickable links.
+    #[arg(long)]
+    pub(crate) autolink: bool,
+
+    /// Underline links in values, such as `[text](url)` markup & emails or URLs turned into
+    /// links.
+    #[arg(long)]
+    pub(crate) underline_links: bool,
+
+    /// Downsample images to this resolution for the size they are drawn at.
+    #[arg(long, value_name = "DPI", value_parser = parse_dpi)]
+    pub(crate) image_dpi: Option<f64>,
+
+    /// Re-encode images as JPEG with this quality, from 1 to 100.
+    #[arg(long, value_name = "QUALITY", value_parser = clap::value_parser!(u8).range(1..=100))]
+    pub(crate) jpeg_quality: Option<u8>,
+
+    /// A file registering fonts with `font,alias,path`, `font_fallback,face,fallback,...` &
+    /// `font_dir,path` lines.
+    #[arg(long, value_name = "FILE")]
+    pub(crate) font_config: Option<PathBuf>,
+
+    /// A directory to look for fonts in, before the system font directories.
+    #[arg(long = "font-dir", value_name = "DIR")]
+    pub(crate) font_directories: Vec<PathBuf>,
+
+    /// Print the license of the fonts embedded in the binary.
+    #[cfg(feature = "bundled-fonts")]
+    #[arg(long)]
+    pub(crate) font_license: bool,
+
+    /// Report the largest assets if the PDF is bigger than this size, such as 2MB.
+    #[arg(long, value_name = "SIZE", value_parser = parse_file_size)]
+    pub(crate) max_size: Option<u64>,
+
+    /// Draw text as vector outlines, so no fonts are embedded in the PDF.
+    #[arg(long)]
+    pub(crate) outline_text: bool,
+
+    /// Keep outlined text as an invisible layer, so it can still be searched & copied.
+    #[arg(long, requires = "outline_text")]
+    pub(crate) text_layer: bool,
+
+    /// The title of the PDF, instead of the name in the input file followed by CV.
+    #[arg(long)]
+    pub(crate) title: Option<String>,
+
+    /// The author of the PDF, instead of the name in the input file.
+    #[arg(long)]
+    pub(crate) author: Option<String>,
+
+    /// The subject of the PDF, instead of "Curriculum vitae".
+    #[arg(long)]
+    pub(crate) subject: Option<String>,
+
+    /// Comma separated keywords for the PDF.
+    #[arg(long, value_delimiter = ',')]
+    pub(crate) keywords: Vec<String>,
+
+    /// The application named as the creator of the PDF, instead of `yaml_cv_rs`.
+    #[arg(long)]
+    pub(crate) creator: Option<String>,
+
+    /// The creation date of the PDF such as 2024-04-01, instead of the date in the input file.
+    #[arg(long)]
+    pub(crate) date: Option<String>,
+
+    /// Make the PDF conform to PDF/A at this level, 1b or 2b, for archiving.
+    #[arg(long, value_name = "LEVEL")]
+    pub(crate) pdfa: Option<Conformance>,
+
+    /// Encrypt the PDF with AES-256, needing this password to open it.
+    #[arg(
+        long,
+        value_name = "PASSWORD",
+        env = "YAML_CV_USER_PASSWORD",
+        hide_env_values = true,
+        conflicts_with = "pdfa"
+    )]
+    pub(crate) user_password: Option<String>,
+
+    /// Encrypt the PDF with AES-256, needing this password to lift its restrictions.
+    #[arg(
+        long,
+        value_name = "PASSWORD",
+        env = "YAML_CV_OWNER_PASSWORD",
+        hide_env_values = true,
+        conflicts_with = "pdfa"
+    )]
+    pub(crate) owner_password: Option<String>,
+
+    /// Comma separated actions an encrypted PDF allows without the owner password, out of print,
+    /// copy, modify, annotate & assemble. Only printing unless set.
+    #[arg(
+        long,
+        value_name = "PERMISSIONS",
+        value_delimiter = ',',
+        conflicts_with = "pdfa"
+    )]
+    pub(crate) allow: Option<Vec<Permission>>,
+
+    /// Make byte-identical PDFs from identical inputs, dated by `SOURCE_DATE_EPOCH` or the creation
+    /// date instead of the clock.
+    #[arg(long)]
+    pub(crate) deterministic: bool,
 }
 
 #[cfg(test)]
@@ -28,4 +173,29 @@ mod tests {
         use clap::CommandFactory;
         Args::command().debug_assert();
     }
+
+    #[test]
+    fn file_sizes_take_units() {
+        assert!(parse_file_size("2MB") == Ok(2 * MEBIBYTE));
+        assert!(parse_file_size(" 300 kb ") == Ok(300 * KIBIBYTE));
+        assert!(parse_file_size("512B") == Ok(512));
+        assert!(parse_file_size("512") == Ok(512));
+    }
+
+    #[test]
+    fn invalid_file_sizes_are_rejected() {
+        assert!(parse_file_size("MB").is_err());
+        assert!(parse_file_size("1.5MB").is_err());
+        assert!(parse_file_size("-1KB").is_err());
+    }
+
+    #[test]
+    fn non_positive_resolutions_are_rejected() {
+        assert!(parse_dpi("150") == Ok(150.0_f64));
+        assert!(parse_dpi(" 72.5 ") == Ok(72.5_f64));
+        assert!(parse_dpi("0").is_err());
+        assert!(parse_dpi("-300").is_err());
+        assert!(parse_dpi("NaN").is_err());
+        assert!(parse_dpi("inf").is_err());
+    }
 }
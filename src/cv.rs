@@ -1,28 +1,64 @@
 //! Creates the CV in a PDF file.
 
 use crate::style::command::{
-    Box, EducationExperience, History, HistoryPosition, Line, Lines, MiscBox, MultiLines, Photo,
-    Text, TextBox, YMBox,
+    Box, EducationExperience, Encryption, History, HistoryPosition, Image as ImageCommand, Line,
+    Lines, Link, MiscBox, MultiLines, Photo, QrCode, Ruby, Text, TextBox, YMBox,
 };
 use crate::style::core::{
-    FontOptions, LineOptions, LineStyle, Point, Size, DEFAULT_FONT_FACE, DEFAULT_FONT_SIZE,
+    Direction, FontOptions, LineOptions, LineStyle, Overflow, Point, Size, DEFAULT_FONT_FACE,
+    DEFAULT_FONT_SIZE, DEFAULT_LINE_HEIGHT, DEFAULT_LINE_WIDTH, DEFAULT_PHOTO_PLACEHOLDER,
+    DEFAULT_PLACEHOLDER_FONT_SIZE,
 };
 use crate::style::Command;
 use crate::yaml::{Entry, YAMLArgs};
-use anyhow::Result;
-use printpdf::image_crate::codecs::jpeg::JpegDecoder;
+use anyhow::{anyhow, Result};
+use printpdf::lopdf::Document as PdfFile;
 use printpdf::{
-    Image, ImageTransform, LineDashPattern, Mm, PdfDocument, PdfDocumentReference,
-    PdfLayerReference, Point as PtPoint,
+    LineDashPattern, Mm, PdfDocument, PdfDocumentReference, PdfLayerReference, Point as PtPoint,
 };
 use std::fs::File;
-use std::io::BufWriter;
-use std::path::Path;
+use std::io::Write;
+use std::path::{Path, PathBuf};
 
-use self::font::{font_size_to_mm, get_fonts, handle_font, FontMap};
-use self::value::{handle_history_value, handle_value};
+use self::encryption::encrypt;
+use self::font::outline::outline_text;
+use self::font::registry::FontRegistry;
+use self::font::subset::subset_fonts;
+#[cfg(feature = "bundled-fonts")]
+pub(crate) use self::font::BUNDLED_FONT_LICENSE;
+use self::font::{
+    font_size_to_mm, get_fonts, resolve_typeface, use_builtin_encodings, FaceRequest, FontMap,
+};
+use self::image::{decode_image, fit_image, place_image};
+use self::link::Links;
+use self::markup::{align_line, draw_rich_line, markup_value, plain_text};
+pub(crate) use self::metadata::MetadataOptions;
+use self::metadata::{identify, Metadata};
+use self::overflow::{measure_excess, shrink_to_fit, wrap};
+pub(crate) use self::pdfa::Conformance;
+use self::pdfa::{make_conformant, mark_binary};
+use self::qr::draw_qr_code;
+use self::report::{report_size, warn};
+use self::resources::Resources;
+use self::ruby::{draw_segments, pair_groups};
+use self::value::{handle_history_value, handle_path, handle_value};
+use self::vector::{load_vector, place_vector};
+use self::vertical::draw_vertical_text;
+mod encryption;
 mod font;
+mod image;
+mod link;
+mod markup;
+mod metadata;
+mod overflow;
+mod pdfa;
+mod qr;
+mod report;
+mod resources;
+mod ruby;
 mod value;
+mod vector;
+mod vertical;
 
 const MARGIN: Mm = Mm(12.7);
 const MARGIN_AS_POINT: Point = Point {
@@ -50,36 +86,89 @@ fn draw_string(
     string: &Text,
     layer: &PdfLayerReference,
     fonts: &FontMap<'_>,
+    links: &Links,
     inputs: &YAMLArgs,
 ) -> Result<()> {
     let font_size = string.font_options.font_size.unwrap_or(DEFAULT_FONT_SIZE);
     let value = handle_value(&string.value, inputs).unwrap_or(&string.value);
-    let default_font = &DEFAULT_FONT_FACE.to_owned();
-    let font = handle_font(
-        string
-            .font_options
-            .font_face
-            .as_ref()
-            .unwrap_or(default_font),
-        fonts,
-    )?;
+    let value = markup_value(value, &string.font_options);
+    let request = FaceRequest::new(&string.font_options);
+    let line_height = string
+        .font_options
+        .line_height
+        .unwrap_or(DEFAULT_LINE_HEIGHT);
+    if string.font_options.direction == Some(Direction::Vertical) {
+        let typeface = resolve_typeface(request, fonts)?;
+        let value = value
+            .split('\n')
+            .map(plain_text)
+            .collect::<Vec<String>>()
+            .join("\n");
+        draw_vertical_text(
+            &value,
+            string.position,
+            &typeface,
+            font_size,
+            line_height,
+            layer,
+        );
+        return Ok(());
+    }
     let font_size_mm = font_size_to_mm(string.font_options.font_size);
+    let align = string.font_options.align.unwrap_or_default();
     // Handle new lines in value
     let mut y_offset = Mm(0.0_f64);
     for line in value.split('\n') {
-        layer.use_text(
+        let (x_offset, line_request) = align_line(
             line,
+            align,
+            string.font_options.width,
+            request,
+            fonts,
             font_size,
-            string.position.x + MARGIN,
-            string.position.y + MARGIN - font_size_mm - y_offset,
-            font,
-        );
-        y_offset += font_size_mm;
+        )?;
+        let origin = Point {
+            x: string.position.x + MARGIN + x_offset,
+            y: string.position.y + MARGIN - font_size_mm - y_offset,
+        };
+        let width = draw_rich_line(line, origin, line_request, font_size, fonts, links, layer)?;
+        if let Some(target) = &string.link {
+            let metrics = fonts.measure(line, request, font_size)?;
+            links.add(
+                (origin.x, origin.y + metrics.descent),
+                (origin.x + width, origin.y + metrics.ascent),
+                target,
+            );
+        }
+        y_offset += font_size_mm * line_height;
     }
 
     Ok(())
 }
 
+#[allow(unused_results, reason = "the width of the drawn text is not needed")]
+fn draw_ruby(
+    ruby: &Ruby,
+    layer: &PdfLayerReference,
+    fonts: &FontMap<'_>,
+    inputs: &YAMLArgs,
+) -> Result<()> {
+    let font_size = ruby.font_options.font_size.unwrap_or(DEFAULT_FONT_SIZE);
+    let value = handle_value(&ruby.value, inputs).unwrap_or(&ruby.value);
+    let reading = handle_value(&ruby.reading, inputs).unwrap_or(&ruby.reading);
+    let typeface = resolve_typeface(FaceRequest::new(&ruby.font_options), fonts)?;
+    let font_size_mm = font_size_to_mm(ruby.font_options.font_size);
+    draw_segments(
+        &pair_groups(value, reading),
+        ruby.position.x + MARGIN,
+        ruby.position.y + MARGIN - font_size_mm,
+        &typeface,
+        font_size,
+        layer,
+    )?;
+    Ok(())
+}
+
 fn draw_line(line: &Line, layer: &PdfLayerReference) {
     let points: std::vec::Vec<(printpdf::Point, _)> = vec![
         ((line.start_position + MARGIN_AS_POINT).into(), false),
@@ -138,29 +227,130 @@ fn draw_box(the_box: &Box, layer: &PdfLayerReference) {
     });
 }
 
-fn load_image(path: &Path) -> Result<Image> {
-    let image_file = File::open(path)?;
-    let image = Image::try_from(JpegDecoder::new(&image_file)?)?;
-    Ok(image)
+/// Draws a frame with instructions where the photo should be pasted.
+fn draw_photo_placeholder(
+    photo: &Photo,
+    layer: &PdfLayerReference,
+    fonts: &FontMap<'_>,
+) -> Result<()> {
+    let line_options = LineOptions {
+        line_width: Some(photo.line_options.line_width.unwrap_or(DEFAULT_LINE_WIDTH)),
+        line_style: Some(photo.line_options.line_style.unwrap_or(LineStyle::Dashed)),
+    };
+    // Boxes are positioned by their bottom left corner
+    let frame = Box {
+        position: Point {
+            x: photo.position.x,
+            y: photo.position.y - photo.size.height,
+        },
+        size: photo.size,
+        line_options,
+    };
+    draw_box(&frame, layer);
+    let typeface = resolve_typeface(FaceRequest::new(&photo.font_options), fonts)?;
+    let font_size = photo
+        .font_options
+        .font_size
+        .unwrap_or(DEFAULT_PLACEHOLDER_FONT_SIZE);
+    let line_height = font_size_to_mm(Some(font_size)) * 1.5_f64;
+    let placeholder = photo
+        .placeholder
+        .as_deref()
+        .unwrap_or(DEFAULT_PHOTO_PLACEHOLDER);
+    let lines: Vec<&str> = placeholder.split("\\n").collect();
+    // Center the lines in the frame
+    let block_height = line_height * f64::from(u32::try_from(lines.len())?);
+    let mut baseline = photo.position.y + MARGIN
+        - (photo.size.height - block_height) / 2.0_f64
+        - typeface.font.ascent(font_size);
+    for line in lines {
+        let width = typeface.text_width(line, font_size)?;
+        let x = photo.position.x + MARGIN + (photo.size.width - width) / 2.0_f64;
+        typeface.draw(line, font_size, x, baseline, layer)?;
+        baseline -= line_height;
+    }
+    Ok(())
 }
 
-fn size_to_scale(size: Mm, position: Mm) -> f64 {
-    let final_pos = position + size;
-    final_pos.0 / position.0
+fn draw_photo(
+    photo: &Photo,
+    image_path: &Path,
+    resources: &Resources,
+    layer: &PdfLayerReference,
+    fonts: &FontMap<'_>,
+) -> Result<()> {
+    if image_path.as_os_str().is_empty() {
+        warn("No photo was given, drawing a placeholder instead");
+        return draw_photo_placeholder(photo, layer, fonts);
+    }
+    if !image_path.is_file() {
+        warn(&format!(
+            "The photo {} was not found, drawing a placeholder instead",
+            image_path.display()
+        ));
+        return draw_photo_placeholder(photo, layer, fonts);
+    }
+    let image = decode_image(image_path)?;
+    let (image, size) = fit_image(image, photo.size, photo.fit.unwrap_or_default());
+    // Center the image in the frame, whose top left corner is the position
+    let position = Point {
+        x: photo.position.x + MARGIN + (photo.size.width - size.width) / 2.0_f64,
+        y: photo.position.y + MARGIN - photo.size.height
+            + (photo.size.height - size.height) / 2.0_f64,
+    };
+    place_image(&image, image_path, position, size, None, resources, layer)
 }
 
-fn draw_photo(photo: &Photo, image_path: &Path, layer: &PdfLayerReference) -> Result<()> {
-    let image = load_image(image_path)?;
-    let transform = ImageTransform {
-        translate_x: Some(photo.position.x + Mm(11.0)),
-        translate_y: Some(photo.position.y - Mm(28.0)),
-        rotate: None,
-        scale_x: Some(size_to_scale(photo.size.width, photo.position.x)),
-        scale_y: Some(size_to_scale(photo.size.height, photo.position.y)),
-        dpi: Some(115.0_f64),
+fn draw_image(
+    image_command: &ImageCommand,
+    resources: &Resources,
+    layer: &PdfLayerReference,
+    inputs: &YAMLArgs,
+) -> Result<()> {
+    let path = handle_path(&image_command.path, inputs)?;
+    let frame = image_command.size;
+    let fit = image_command.fit.unwrap_or_default();
+    if let Some(vector) = load_vector(&path, image_command.page)? {
+        let frame_position = Point {
+            x: image_command.position.x + MARGIN,
+            y: image_command.position.y + MARGIN - frame.height,
+        };
+        place_vector(
+            vector,
+            frame_position,
+            frame,
+            fit,
+            image_command.opacity,
+            resources,
+            layer,
+        );
+        return Ok(());
+    }
+    let (image, size) = fit_image(decode_image(&path)?, frame, fit);
+    // Center the image in the frame, whose top left corner is the position
+    let position = Point {
+        x: image_command.position.x + MARGIN + (frame.width - size.width) / 2.0_f64,
+        y: image_command.position.y + MARGIN - frame.height
+            + (frame.height - size.height) / 2.0_f64,
     };
-    image.add_to_layer(layer.clone(), transform);
-    Ok(())
+    place_image(
+        &image,
+        &path,
+        position,
+        size,
+        image_command.opacity,
+        resources,
+        layer,
+    )
+}
+
+fn draw_qrcode(qr_code: &QrCode, layer: &PdfLayerReference, inputs: &YAMLArgs) -> Result<()> {
+    let value = handle_value(&qr_code.value, inputs)?;
+    let top_left = Point {
+        x: qr_code.position.x + MARGIN,
+        y: qr_code.position.y + MARGIN,
+    };
+    draw_qr_code(qr_code, value, top_left, layer)
 }
 
 fn new_page(doc: &PdfDocumentReference) -> PdfLayerReference {
@@ -168,21 +358,103 @@ fn new_page(doc: &PdfDocumentReference) -> PdfLayerReference {
     doc.get_page(new_page).get_layer(new_layer)
 }
 
+fn clip_to_textbox(textbox: &TextBox, layer: &PdfLayerReference) {
+    let left = textbox.position.x + MARGIN;
+    let right = left + textbox.size.width;
+    let top = textbox.position.y + MARGIN;
+    let bottom = top - textbox.size.height;
+    let points: std::vec::Vec<(printpdf::Point, _)> = vec![
+        (PtPoint::new(left, bottom), false),
+        (PtPoint::new(right, bottom), false),
+        (PtPoint::new(right, top), false),
+        (PtPoint::new(left, top), false),
+    ];
+    layer.add_shape(printpdf::Line {
+        points,
+        is_closed: true,
+        has_fill: false,
+        has_stroke: false,
+        is_clipping_path: true,
+    });
+}
+
 fn draw_textbox(
     textbox: &TextBox,
     layer: &PdfLayerReference,
     fonts: &FontMap<'_>,
+    links: &Links,
     inputs: &YAMLArgs,
 ) -> Result<()> {
+    let mut value =
+        markup_value(handle_value(&textbox.value, inputs)?, &textbox.font_options).into_owned();
+    let mut font_options = textbox.font_options.clone();
+    // The value is escaped already, so it is drawn as markup
+    font_options.markup = Some(true);
+    let request = FaceRequest::new(&textbox.font_options);
+    let font_size = font_options.font_size.unwrap_or(DEFAULT_FONT_SIZE);
+    match textbox.overflow {
+        Some(Overflow::Shrink) => {
+            let fitted = shrink_to_fit(textbox, &value, request, fonts, font_size)?;
+            if let Some(excess) = fitted.excess {
+                warn(&format!(
+                    "{} even at a font size of {}",
+                    excess.describe(textbox),
+                    fitted.font_size
+                ));
+            }
+            font_options.font_size = Some(fitted.font_size);
+            value = fitted.value;
+        }
+        Some(Overflow::Clip) => {
+            value = wrap(textbox, &value, request, fonts, font_size)?;
+            layer.save_graphics_state();
+            clip_to_textbox(textbox, layer);
+        }
+        Some(overflow @ (Overflow::Error | Overflow::Warn)) => {
+            value = wrap(textbox, &value, request, fonts, font_size)?;
+            let excess = measure_excess(textbox, &value, request, fonts, font_size)?;
+            if !excess.fits() {
+                let message = excess.describe(textbox);
+                if overflow == Overflow::Error {
+                    return Err(anyhow!(message));
+                }
+                warn(&message);
+            }
+        }
+        None => {}
+    }
+    let mut position = textbox.position;
+    if font_options.direction == Some(Direction::Vertical) {
+        // Vertical text starts from the right edge of the box
+        position.x += textbox.size.width - font_size_to_mm(font_options.font_size);
+    } else if font_options.width.is_none() {
+        // Align lines within the box
+        font_options.width = Some(textbox.size.width);
+    }
     let string = Text {
-        position: textbox.position,
-        value: handle_value(&textbox.value, inputs)?.to_string(),
-        font_options: textbox.font_options.clone(),
+        position,
+        value,
+        font_options,
+        link: textbox.link.clone(),
     };
-    draw_string(&string, layer, fonts, inputs)?;
+    draw_string(&string, layer, fonts, links, inputs)?;
+    if textbox.overflow == Some(Overflow::Clip) {
+        layer.restore_graphics_state();
+    }
     Ok(())
 }
 
+fn draw_link(link: &Link, links: &Links) {
+    links.add(
+        (link.position.x + MARGIN, link.position.y + MARGIN),
+        (
+            link.position.x + link.size.width + MARGIN,
+            link.position.y + link.size.height + MARGIN,
+        ),
+        &link.target,
+    );
+}
+
 fn draw_multilines(multilines: &MultiLines, layer: &PdfLayerReference) {
     let mut pos = multilines.start_position;
     for __i in 0..multilines.stroke_number {
@@ -197,21 +469,31 @@ fn draw_multilines(multilines: &MultiLines, layer: &PdfLayerReference) {
 }
 
 #[allow(
-    clippy::cast_precision_loss,
-    clippy::as_conversions,
-    clippy::too_many_lines
+    clippy::too_many_lines,
+    reason = "the layout is a straight port of the original"
 )]
 fn draw_ymbox(
     ymbox: &YMBox,
     layer: &PdfLayerReference,
     fonts: &FontMap<'_>,
+    links: &Links,
     inputs: &YAMLArgs,
 ) -> Result<()> {
     let sy = 7.0_f64;
     let num: Mm = Mm(ymbox.num.into());
     let dy: Mm = (num + Mm(1.0_f64)) * sy;
-    let title_length: f64 = ymbox.title.len() as f64;
-    let namepos = Mm(104.0 - title_length * 1.7);
+    let font_size_nine = FontOptions {
+        font_size: Some(9.0_f64),
+        font_face: Some("mincho".to_owned()),
+        ..FontOptions::default()
+    };
+    // Center the title between the month column & the right edge
+    let title = fonts.measure(
+        &ymbox.title,
+        FaceRequest::new(&font_size_nine),
+        font_size_nine.font_size.unwrap_or(DEFAULT_FONT_SIZE),
+    )?;
+    let namepos = Mm(104.0) - title.advance / 2.0_f64;
     draw_box(
         &Box {
             position: Point {
@@ -292,12 +574,9 @@ fn draw_ymbox(
         },
         layer,
         fonts,
+        links,
         inputs,
     )?;
-    let font_size_nine = FontOptions {
-        font_size: Some(9.0_f64),
-        font_face: Some("mincho".to_owned()),
-    };
     draw_string(
         &Text {
             position: Point {
@@ -306,9 +585,11 @@ fn draw_ymbox(
             },
             value: "年".to_owned(),
             font_options: font_size_nine.clone(),
+            link: None,
         },
         layer,
         fonts,
+        links,
         inputs,
     )?;
     draw_string(
@@ -319,9 +600,11 @@ fn draw_ymbox(
             },
             value: "月".to_owned(),
             font_options: font_size_nine.clone(),
+            link: None,
         },
         layer,
         fonts,
+        links,
         inputs,
     )?;
     draw_string(
@@ -332,9 +615,11 @@ fn draw_ymbox(
             },
             value: ymbox.title.clone(),
             font_options: font_size_nine,
+            link: None,
         },
         layer,
         fonts,
+        links,
         inputs,
     )?;
     Ok(())
@@ -344,7 +629,7 @@ fn draw_lines(lines: &Lines, layer: &PdfLayerReference) -> Result<()> {
     let is_closed = lines.close.unwrap_or(true);
     let start_position: Point = *lines
         .positions
-        .get(0)
+        .first()
         .expect("Failed to get first position in lines");
     let mut points: std::vec::Vec<(printpdf::Point, _)> =
         vec![((start_position + MARGIN_AS_POINT).into(), false)];
@@ -366,6 +651,10 @@ fn draw_lines(lines: &Lines, layer: &PdfLayerReference) -> Result<()> {
     Ok(())
 }
 
+#[allow(
+    clippy::too_many_arguments,
+    reason = "the table is drawn with the same context as the strings in it"
+)]
 fn draw_table(
     header: Option<&Text>,
     table: &[Entry],
@@ -373,15 +662,16 @@ fn draw_table(
     font_options: &FontOptions,
     layer: &PdfLayerReference,
     fonts: &FontMap<'_>,
+    links: &Links,
     inputs: &YAMLArgs,
 ) -> Result<Mm> {
     let mut final_y = positions.y + positions.padding;
     if let Some(header_ref) = header {
-        draw_string(header_ref, layer, fonts, inputs)?;
+        draw_string(header_ref, layer, fonts, links, inputs)?;
         final_y = header_ref.position.y - positions.padding;
     }
     let font_size_mm = font_size_to_mm(font_options.font_size);
-    for entry in table.iter() {
+    for entry in table {
         let year = Text {
             position: Point {
                 x: positions.year_x,
@@ -389,12 +679,13 @@ fn draw_table(
             },
             value: entry.year.clone().unwrap_or_default(),
             font_options: font_options.clone(),
+            link: None,
         };
-        draw_string(&year, layer, fonts, inputs)?;
+        draw_string(&year, layer, fonts, links, inputs)?;
         let month_value: String = if let Some(month) = entry.month {
             month.to_string()
         } else {
-            "".to_owned()
+            String::new()
         };
         let month_offset = if month_value.len() > 1 {
             font_size_mm / 3.0_f64
@@ -408,8 +699,9 @@ fn draw_table(
             },
             value: month_value,
             font_options: font_options.clone(),
+            link: None,
         };
-        draw_string(&month, layer, fonts, inputs)?;
+        draw_string(&month, layer, fonts, links, inputs)?;
         let value = Text {
             position: Point {
                 x: positions.value_x,
@@ -417,8 +709,9 @@ fn draw_table(
             },
             value: entry.value.clone(),
             font_options: font_options.clone(),
+            link: None,
         };
-        draw_string(&value, layer, fonts, inputs)?;
+        draw_string(&value, layer, fonts, links, inputs)?;
         final_y -= positions.padding;
     }
     Ok(final_y)
@@ -428,6 +721,7 @@ fn draw_education_experience(
     education_experience: &EducationExperience,
     layer: &PdfLayerReference,
     fonts: &FontMap<'_>,
+    links: &Links,
     inputs: &YAMLArgs,
 ) -> Result<()> {
     let education_header = Text {
@@ -437,6 +731,7 @@ fn draw_education_experience(
         },
         value: "学歴".to_owned(),
         font_options: education_experience.font_options.clone(),
+        link: None,
     };
     let mut current_y = draw_table(
         Some(&education_header),
@@ -445,6 +740,7 @@ fn draw_education_experience(
         &education_experience.font_options,
         layer,
         fonts,
+        links,
         inputs,
     )?;
     let experience_header = Text {
@@ -454,6 +750,7 @@ fn draw_education_experience(
         },
         value: "職歴".to_owned(),
         font_options: education_experience.font_options.clone(),
+        link: None,
     };
     current_y = draw_table(
         Some(&experience_header),
@@ -462,6 +759,7 @@ fn draw_education_experience(
         &education_experience.font_options,
         layer,
         fonts,
+        links,
         inputs,
     )?;
     let ijou = Text {
@@ -471,20 +769,31 @@ fn draw_education_experience(
         },
         value: "以上".to_owned(),
         font_options: education_experience.font_options.clone(),
+        link: None,
     };
-    draw_string(&ijou, layer, fonts, inputs)?;
+    draw_string(&ijou, layer, fonts, links, inputs)?;
     Ok(())
 }
 
-#[allow(clippy::cast_precision_loss, clippy::as_conversions)]
 fn draw_miscbox(
     miscbox: &MiscBox,
     layer: &PdfLayerReference,
     fonts: &FontMap<'_>,
+    links: &Links,
     inputs: &YAMLArgs,
 ) -> Result<()> {
-    let name_length: f64 = miscbox.title.len() as f64;
-    let namepos = Mm(88.5_f64 - (name_length * 0.6_f64));
+    let font_options = FontOptions {
+        font_size: Some(9.0_f64),
+        font_face: Some(DEFAULT_FONT_FACE.to_owned()),
+        ..FontOptions::default()
+    };
+    // Center the title in the box
+    let name = fonts.measure(
+        &miscbox.title,
+        FaceRequest::new(&font_options),
+        font_options.font_size.unwrap_or(DEFAULT_FONT_SIZE),
+    )?;
+    let namepos = Mm(88.5_f64) - name.advance / 2.0_f64;
     draw_string(
         &Text {
             position: Point {
@@ -492,13 +801,12 @@ fn draw_miscbox(
                 y: miscbox.y + miscbox.height - Mm(2.0),
             },
             value: miscbox.title.clone(),
-            font_options: FontOptions {
-                font_size: Some(9.0_f64),
-                font_face: Some(DEFAULT_FONT_FACE.to_owned()),
-            },
+            font_options,
+            link: None,
         },
         layer,
         fonts,
+        links,
         inputs,
     )?;
     draw_line(
@@ -527,9 +835,13 @@ fn draw_miscbox(
             },
             value: miscbox.value.clone(),
             font_options: miscbox.font_options.clone(),
+            overflow: miscbox.overflow,
+            link: None,
+            line_number: miscbox.line_number,
         },
         layer,
         fonts,
+        links,
         inputs,
     )?;
     draw_box(
@@ -552,11 +864,12 @@ fn draw_miscbox(
     Ok(())
 }
 
-#[allow(unused_results)]
+#[allow(unused_results, reason = "the final y position is not needed")]
 fn draw_history(
     history: &History,
     layer: &PdfLayerReference,
     fonts: &FontMap<'_>,
+    links: &Links,
     inputs: &YAMLArgs,
 ) -> Result<()> {
     draw_table(
@@ -566,38 +879,162 @@ fn draw_history(
         &history.font_options,
         layer,
         fonts,
+        links,
         inputs,
     )?;
     Ok(())
 }
 
+/// The options for how the CV is made, from the command line.
+#[allow(
+    clippy::struct_excessive_bools,
+    reason = "the options mirror the flags of the command line"
+)]
+pub(crate) struct Options {
+    /// Whether to turn emails & URLs in values into links.
+    pub(crate) autolink: bool,
+    /// Whether to underline links in values.
+    pub(crate) underline_links: bool,
+    /// The resolution to downsample images to.
+    pub(crate) image_dpi: Option<f64>,
+    /// The quality to re-encode images as JPEG with.
+    pub(crate) jpeg_quality: Option<u8>,
+    /// The size in bytes over which the largest assets are reported.
+    pub(crate) max_size: Option<u64>,
+    /// The directories to look for fonts in before the system ones.
+    pub(crate) font_directories: Vec<PathBuf>,
+    /// Whether to draw text as vector outlines instead of with embedded fonts.
+    pub(crate) outline_text: bool,
+    /// Whether to keep outlined text as an invisible layer.
+    pub(crate) text_layer: bool,
+    /// The metadata overriding the name & date from the input file.
+    pub(crate) metadata: MetadataOptions,
+    /// The PDF/A level to make the PDF conform to.
+    pub(crate) pdfa: Option<Conformance>,
+    /// The passwords & permissions to encrypt the PDF with, over those of the style file.
+    pub(crate) encryption: Encryption,
+    /// Whether identical inputs make byte-identical PDFs, dated without the clock.
+    pub(crate) deterministic: bool,
+}
+
+/// Splits the commands that configure the document from the ones that draw on it,
+/// returning the registered fonts, the encryption & the commands to draw.
+fn configure(
+    style_script: Vec<Command>,
+    options: &Options,
+) -> (FontRegistry, Encryption, Vec<Command>) {
+    let mut registry = FontRegistry::new(options.font_directories.clone());
+    let mut encryption = options.encryption.clone();
+    let mut drawn_commands = Vec::new();
+    for command in style_script {
+        match command {
+            Command::Font(font) => registry.register(font),
+            Command::FontFallback(fallback) => registry.set_fallback(fallback),
+            Command::FontDirectory(directory) => registry.add_directory(directory),
+            Command::Encryption(config) => encryption = encryption.or(config),
+            _ => drawn_commands.push(command),
+        }
+    }
+    (registry, encryption, drawn_commands)
+}
+
 pub(crate) fn make(
     output_path: &Path,
     style_script: Vec<Command>,
     inputs: &YAMLArgs,
+    options: &Options,
 ) -> Result<()> {
     let (doc, page1, layer1) = PdfDocument::new("CV", Mm(A4_WIDTH), Mm(A4_HEIGHT), "Layer 1");
     let mut current_layer = doc.get_page(page1).get_layer(layer1);
-    let fonts = get_fonts(&doc)?;
-    let image_path = Path::new("./photo.jpg");
-    for command in style_script {
+    let (registry, encryption, drawn_commands) = configure(style_script, options);
+    if let (Some(conformance), true) = (options.pdfa, encryption.is_enabled()) {
+        return Err(anyhow!(
+            "PDF/A-{conformance} forbids encryption; remove the encrypt command or --pdfa"
+        ));
+    }
+    let fonts = get_fonts(&doc, &registry)?;
+    let annotations = Links::new(options.autolink, options.underline_links);
+    let resources = Resources::new(options.image_dpi, options.jpeg_quality);
+    for command in drawn_commands {
         match command {
-            Command::Text(text) => draw_string(&text, &current_layer, &fonts, inputs)?,
+            Command::Text(text) => {
+                draw_string(&text, &current_layer, &fonts, &annotations, inputs)?;
+            }
+            Command::Ruby(ruby) => draw_ruby(&ruby, &current_layer, &fonts, inputs)?,
             Command::Line(line) => draw_line(&line, &current_layer),
             Command::Box(the_box) => draw_box(&the_box, &current_layer),
-            Command::Photo(photo) => draw_photo(&photo, image_path, &current_layer)?,
-            Command::NewPage => current_layer = new_page(&doc),
-            Command::TextBox(textbox) => draw_textbox(&textbox, &current_layer, &fonts, inputs)?,
+            Command::Photo(photo) => {
+                draw_photo(&photo, &inputs.photo, &resources, &current_layer, &fonts)?;
+            }
+            Command::QrCode(qr_code) => draw_qrcode(&qr_code, &current_layer, inputs)?,
+            Command::Image(image_command) => {
+                draw_image(&image_command, &resources, &current_layer, inputs)?;
+            }
+            Command::NewPage => {
+                current_layer = new_page(&doc);
+                annotations.next_page();
+                resources.next_page();
+            }
+            Command::TextBox(textbox) => {
+                draw_textbox(&textbox, &current_layer, &fonts, &annotations, inputs)?;
+            }
             Command::MultiLines(multilines) => draw_multilines(&multilines, &current_layer),
-            Command::YMBox(ymbox) => draw_ymbox(&ymbox, &current_layer, &fonts, inputs)?,
-            Command::MiscBox(miscbox) => draw_miscbox(&miscbox, &current_layer, &fonts, inputs)?,
-            Command::History(history) => draw_history(&history, &current_layer, &fonts, inputs)?,
-            Command::EducationExperience(education_experience) => {
-                draw_education_experience(&education_experience, &current_layer, &fonts, inputs)?;
+            Command::YMBox(ymbox) => {
+                draw_ymbox(&ymbox, &current_layer, &fonts, &annotations, inputs)?;
+            }
+            Command::MiscBox(miscbox) => {
+                draw_miscbox(&miscbox, &current_layer, &fonts, &annotations, inputs)?;
             }
+            Command::History(history) => {
+                draw_history(&history, &current_layer, &fonts, &annotations, inputs)?;
+            }
+            Command::EducationExperience(education_experience) => draw_education_experience(
+                &education_experience,
+                &current_layer,
+                &fonts,
+                &annotations,
+                inputs,
+            )?,
             Command::Lines(lines) => draw_lines(&lines, &current_layer)?,
+            Command::Link(link) => draw_link(&link, &annotations),
+            Command::Font(_)
+            | Command::FontFallback(_)
+            | Command::FontDirectory(_)
+            | Command::Encryption(_) => {}
         }
     }
-    doc.save(&mut BufWriter::new(File::create(output_path)?))?;
+    let drawn_text = fonts.into_drawn_text();
+    let mut document = PdfFile::load_mem(&doc.save_to_bytes()?)?;
+    annotations.add_to_document(&mut document)?;
+    resources.add_to_document(&mut document)?;
+    subset_fonts(&mut document, &drawn_text)?;
+    use_builtin_encodings(&mut document);
+    if options.outline_text {
+        outline_text(&mut document, options.text_layer)?;
+    }
+    Metadata::new(
+        &options.metadata,
+        &inputs.name,
+        &inputs.date,
+        options.pdfa,
+        options.deterministic,
+    )?
+    .add_to_document(&mut document)?;
+    if let Some(conformance) = options.pdfa {
+        make_conformant(&mut document, conformance, &resources)?;
+    }
+    identify(&mut document)?;
+    if encryption.is_enabled() {
+        encrypt(&mut document, &encryption, options.deterministic)?;
+    }
+    let mut pdf = Vec::new();
+    document.save_to(&mut pdf)?;
+    if options.pdfa.is_some() {
+        mark_binary(&mut pdf)?;
+    }
+    if let Some(max_size) = options.max_size {
+        report_size(&document, u64::try_from(pdf.len())?, max_size, &resources);
+    }
+    File::create(output_path)?.write_all(&pdf)?;
     Ok(())
 }
@@ -0,0 +1,316 @@
+//! Encrypts the saved PDF with AES-256, the standard security handler of revision 6, so it can
+//! only be opened with the user password & only changed as its permissions allow without the
+//! owner password.
+
+use crate::style::command::Encryption;
+use crate::style::core::Permission;
+use aes::cipher::block_padding::{NoPadding, Pkcs7};
+use aes::cipher::{BlockEncrypt, BlockEncryptMut, KeyInit, KeyIvInit};
+use aes::{Aes128, Aes256};
+use anyhow::{anyhow, Result};
+use printpdf::lopdf::{dictionary, Document, Object, StringFormat};
+use sha2::{Digest, Sha256, Sha384, Sha512};
+
+/// The longest a password may be in bytes, as longer ones are cut short by readers.
+const MAX_PASSWORD_LENGTH: usize = 127;
+/// The permission bits that are always set, as the specification reserves them.
+const RESERVED_PERMISSIONS: u32 = 0xffff_f0c0;
+/// The permission to extract text for accessibility, always granted so screen readers still work.
+const ACCESSIBILITY: u32 = 1 << 9;
+const AES_BLOCK_SIZE: usize = 16;
+
+/// The permission bits granted by a permission.
+const fn permission_bits(permission: Permission) -> u32 {
+    match permission {
+        // Both printing at all & printing at full quality
+        Permission::Print => 1 << 2 | 1 << 11,
+        Permission::Modify => 1 << 3,
+        Permission::Copy => 1 << 4,
+        // Both adding comments & filling in forms
+        Permission::Annotate => 1 << 5 | 1 << 8,
+        Permission::Assemble => 1 << 10,
+    }
+}
+
+/// Where the keys, salts & initialization vectors come from.
+struct KeySource {
+    /// The secret the bytes are derived from in deterministic PDFs, instead of random numbers.
+    seed: Option<Vec<u8>>,
+    /// The blocks of bytes made from the seed so far, so no two are the same.
+    blocks: usize,
+}
+
+impl KeySource {
+    /// Makes unpredictable bytes, from the seed if there is one or the system's random numbers.
+    fn bytes(&mut self, length: usize) -> Result<Vec<u8>> {
+        let mut bytes = Vec::new();
+        let Some(seed) = &self.seed else {
+            bytes.resize(length, 0);
+            getrandom::fill(&mut bytes)
+                .map_err(|error| anyhow!("Failed to get random numbers for the keys: {error}"))?;
+            return Ok(bytes);
+        };
+        while bytes.len() < length {
+            bytes.extend(
+                Sha256::new()
+                    .chain_update(self.blocks.to_le_bytes())
+                    .chain_update(seed)
+                    .finalize(),
+            );
+            self.blocks += 1;
+        }
+        bytes.truncate(length);
+        Ok(bytes)
+    }
+}
+
+/// The bytes of a password, cut short to the longest length readers accept.
+fn password_bytes(password: &str) -> &[u8] {
+    let bytes = password.as_bytes();
+    bytes.get(..MAX_PASSWORD_LENGTH).unwrap_or(bytes)
+}
+
+/// Hashes a password with a salt & the user key, as the user & owner keys are made
+/// (ISO 32000-2, algorithm 2.B).
+fn hash(password: &[u8], salt: &[u8], user_key: &[u8]) -> Result<Vec<u8>> {
+    let mut key = Sha256::new()
+        .chain_update(password)
+        .chain_update(salt)
+        .chain_update(user_key)
+        .finalize()
+        .to_vec();
+    let mut round = 0_usize;
+    loop {
+        let repeated: Vec<u8> = [password, &key, user_key].concat().repeat(64);
+        let encrypted = cbc::Encryptor::<Aes128>::new_from_slices(
+            key.get(..AES_BLOCK_SIZE).unwrap_or_default(),
+            key.get(AES_BLOCK_SIZE..AES_BLOCK_SIZE * 2)
+                .unwrap_or_default(),
+        )
+        .map_err(|error| anyhow!("Failed to hash a password: {error}"))?
+        .encrypt_padded_vec_mut::<NoPadding>(&repeated);
+        // The first 16 bytes as a number modulo 3, which is their sum modulo 3
+        let remainder = encrypted
+            .iter()
+            .take(AES_BLOCK_SIZE)
+            .map(|byte| u32::from(*byte))
+            .sum::<u32>()
+            .rem_euclid(3);
+        key = match remainder {
+            0 => Sha256::digest(&encrypted).to_vec(),
+            1 => Sha384::digest(&encrypted).to_vec(),
+            _ => Sha512::digest(&encrypted).to_vec(),
+        };
+        round += 1;
+        let last = encrypted.last().copied().unwrap_or_default();
+        if round >= 64 && usize::from(last) + 32 <= round {
+            break;
+        }
+    }
+    key.truncate(32);
+    Ok(key)
+}
+
+/// Encrypts data with AES-256 in CBC mode, without padding & a zero initialization vector,
+/// as the file key is encrypted with the user & owner keys.
+fn encrypt_key(key: &[u8], data: &[u8]) -> Result<Vec<u8>> {
+    Ok(
+        cbc::Encryptor::<Aes256>::new_from_slices(key, &[0; AES_BLOCK_SIZE])
+            .map_err(|error| anyhow!("Failed to encrypt the file key: {error}"))?
+            .encrypt_padded_vec_mut::<NoPadding>(data),
+    )
+}
+
+/// Encrypts a string or stream with the file key, prefixed by its initialization vector.
+fn encrypt_data(key: &[u8], source: &mut KeySource, data: &[u8]) -> Result<Vec<u8>> {
+    let iv = source.bytes(AES_BLOCK_SIZE)?;
+    let encrypted = cbc::Encryptor::<Aes256>::new_from_slices(key, &iv)
+        .map_err(|error| anyhow!("Failed to encrypt the PDF: {error}"))?
+        .encrypt_padded_vec_mut::<Pkcs7>(data);
+    Ok([iv, encrypted].concat())
+}
+
+/// Encrypts every string & stream in an object.
+fn encrypt_object(key: &[u8], source: &mut KeySource, object: &mut Object) -> Result<()> {
+    match object {
+        Object::String(bytes, format) => {
+            *bytes = encrypt_data(key, source, bytes)?;
+            // Written as hexadecimal, so no byte is read back differently
+            *format = StringFormat::Hexadecimal;
+        }
+        Object::Array(items) => {
+            for item in items {
+                encrypt_object(key, source, item)?;
+            }
+        }
+        Object::Dictionary(dictionary) => {
+            for (_, value) in dictionary.iter_mut() {
+                encrypt_object(key, source, value)?;
+            }
+        }
+        Object::Stream(stream) => {
+            for (_, value) in stream.dict.iter_mut() {
+                encrypt_object(key, source, value)?;
+            }
+            let content = encrypt_data(key, source, &stream.content)?;
+            stream.set_content(content);
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Makes the secret the keys of a deterministic PDF are derived from, out of its passwords &
+/// identifier.
+fn seed(document: &Document, encryption: &Encryption) -> Result<Vec<u8>> {
+    let owner_password = encryption.owner_password.as_deref().ok_or_else(|| {
+        anyhow!("Encrypting a deterministic PDF needs an owner password to make its keys from")
+    })?;
+    let identifier = document
+        .trailer
+        .get(b"ID")
+        .and_then(Object::as_array)?
+        .first()
+        .and_then(|part| part.as_str().ok())
+        .unwrap_or_default();
+    let mut seed = Sha256::new();
+    for part in [
+        password_bytes(encryption.user_password.as_deref().unwrap_or_default()),
+        password_bytes(owner_password),
+        identifier,
+    ] {
+        seed.update(part.len().to_le_bytes());
+        seed.update(part);
+    }
+    Ok(seed.finalize().to_vec())
+}
+
+/// Encrypts every string & stream of the document & adds the encryption dictionary.
+/// Without an owner password, a random one is used, so the permissions cannot be lifted.
+/// The keys of a `deterministic` PDF are made from its passwords & identifier instead of random
+/// numbers, so it needs an owner password to keep them secret.
+pub(crate) fn encrypt(
+    document: &mut Document,
+    encryption: &Encryption,
+    deterministic: bool,
+) -> Result<()> {
+    let user_password = password_bytes(encryption.user_password.as_deref().unwrap_or_default());
+    let mut source = KeySource {
+        seed: deterministic
+            .then(|| seed(document, encryption))
+            .transpose()?,
+        blocks: 0,
+    };
+    let file_key = source.bytes(32)?;
+    let random_owner_password = source.bytes(32)?;
+    let owner_password = encryption
+        .owner_password
+        .as_deref()
+        .map_or(random_owner_password.as_slice(), password_bytes);
+
+    // The validation & key salts of the user & owner passwords
+    let salts = source.bytes(32)?;
+    let (user_salts, owner_salts) = salts.split_at(16);
+    let (user_validation_salt, user_key_salt) = user_salts.split_at(8);
+    let (owner_validation_salt, owner_key_salt) = owner_salts.split_at(8);
+    let user_key = [&hash(user_password, user_validation_salt, &[])?, user_salts].concat();
+    let user_encrypted_key = encrypt_key(&hash(user_password, user_key_salt, &[])?, &file_key)?;
+    let owner_key = [
+        &hash(owner_password, owner_validation_salt, &user_key)?,
+        owner_salts,
+    ]
+    .concat();
+    let owner_encrypted_key =
+        encrypt_key(&hash(owner_password, owner_key_salt, &user_key)?, &file_key)?;
+
+    let permissions = encryption
+        .permissions
+        .as_deref()
+        .unwrap_or(&[Permission::Print])
+        .iter()
+        .fold(RESERVED_PERMISSIONS | ACCESSIBILITY, |bits, permission| {
+            bits | permission_bits(*permission)
+        });
+    // The permissions encrypted with the file key, so changing them can be detected
+    let mut permission_block = [0_u8; AES_BLOCK_SIZE];
+    for (byte, value) in permission_block.iter_mut().zip(
+        permissions
+            .to_le_bytes()
+            .into_iter()
+            .chain([0xff; 4])
+            .chain(*b"Tadb")
+            .chain(source.bytes(4)?),
+    ) {
+        *byte = value;
+    }
+    Aes256::new_from_slice(&file_key)
+        .map_err(|error| anyhow!("Failed to encrypt the permissions: {error}"))?
+        .encrypt_block((&mut permission_block).into());
+
+    for object in document.objects.values_mut() {
+        encrypt_object(&file_key, &mut source, object)?;
+    }
+    let encrypt_id = document.add_object(dictionary! {
+        "Filter" => "Standard",
+        "V" => 5_i64,
+        "R" => 6_i64,
+        "Length" => 256_i64,
+        "CF" => dictionary! {
+            "StdCF" => dictionary! {
+                "AuthEvent" => "DocOpen",
+                "CFM" => "AESV3",
+                "Length" => 32_i64,
+            },
+        },
+        "StmF" => "StdCF",
+        "StrF" => "StdCF",
+        "O" => Object::String(owner_key, StringFormat::Hexadecimal),
+        "U" => Object::String(user_key, StringFormat::Hexadecimal),
+        "OE" => Object::String(owner_encrypted_key, StringFormat::Hexadecimal),
+        "UE" => Object::String(user_encrypted_key, StringFormat::Hexadecimal),
+        "P" => i64::from(i32::from_le_bytes(permissions.to_le_bytes())),
+        "Perms" => Object::String(permission_block.to_vec(), StringFormat::Hexadecimal),
+    });
+    document.trailer.set("Encrypt", encrypt_id);
+    // AES-256 is an extension of PDF 1.7
+    "1.7".clone_into(&mut document.version);
+    let catalog_id = document.trailer.get(b"Root")?.as_reference()?;
+    document.get_object_mut(catalog_id)?.as_dict_mut()?.set(
+        "Extensions",
+        dictionary! {
+            "ADBE" => dictionary! {
+                "BaseVersion" => "1.7",
+                "ExtensionLevel" => 8_i64,
+            },
+        },
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn user_passwords_hash_to_the_known_key() -> Result<()> {
+        let expected = [
+            0x74, 0x0e, 0x2b, 0x0a, 0x00, 0x01, 0xed, 0x6b, 0x0f, 0x58, 0x7d, 0x85, 0xf7, 0xf6,
+            0x1b, 0xbd, 0x55, 0x5a, 0x36, 0xce, 0xe3, 0x85, 0x7d, 0x5d, 0xa1, 0x45, 0x24, 0x3b,
+            0x20, 0x21, 0x12, 0xb7,
+        ];
+        assert!(hash(b"password", b"saltsalt", &[])? == expected);
+        Ok(())
+    }
+
+    #[test]
+    fn owner_passwords_hash_with_the_user_key() -> Result<()> {
+        let user_key: Vec<u8> = (0..48).collect();
+        let expected = [
+            0xce, 0xc1, 0x7c, 0x6c, 0xc5, 0x98, 0xce, 0x68, 0x41, 0xeb, 0x21, 0x3d, 0x17, 0x0b,
+            0xb1, 0x45, 0x1a, 0x35, 0x73, 0x82, 0xee, 0x4f, 0xfc, 0xd0, 0x4c, 0xd8, 0xe1, 0x3b,
+            0x7f, 0x6a, 0x12, 0x93,
+        ];
+        assert!(hash(b"owner", b"01234567", &user_key)? == expected);
+        Ok(())
+    }
+}
@@ -1,35 +1,991 @@
 //! Contains functions to get & process fonts.
 
-use crate::style::core::DEFAULT_FONT_SIZE;
-use anyhow::{anyhow, Result};
-use printpdf::{BuiltinFont, IndirectFontRef, Mm, PdfDocumentReference, Pt};
-use std::collections::HashMap;
-use std::fs::File;
-
-pub(crate) type FontMap<'a> = HashMap<&'a str, IndirectFontRef>;
-#[allow(unused_results)]
-pub(crate) fn get_fonts<'a>(doc: &PdfDocumentReference) -> Result<FontMap<'a>> {
-    let mut fonts = HashMap::new();
-    fonts.insert(
+use crate::style::command::Font;
+use crate::style::core::{
+    FontOptions, FontStyle, FontWeight, DEFAULT_FONT_FACE, DEFAULT_FONT_SIZE,
+};
+use anyhow::{anyhow, Context, Result};
+use printpdf::lopdf::content::Operation;
+use printpdf::lopdf::{Document, Object, StringFormat};
+use printpdf::{
+    BuiltinFont, IndirectFontRef, Mm, PdfDocumentReference, PdfLayerReference, Pt, TextMatrix,
+    TextRenderingMode,
+};
+use std::cell::{OnceCell, RefCell};
+use std::collections::{BTreeMap, HashMap};
+use std::fs::read;
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+use std::{iter, ptr};
+use ttf_parser::gsub::{SingleSubstitution, SubstitutionSubtable};
+use ttf_parser::{Face, GlyphId, Tag};
+
+use self::builtin::BuiltinMetrics;
+use self::parsed::ParsedFace;
+use self::registry::FontRegistry;
+use super::report::warn;
+mod builtin;
+pub(crate) mod outline;
+mod parsed;
+pub(crate) mod registry;
+pub(crate) mod subset;
+
+/// The file names of the fonts used for `mincho` & `gothic` unless they are registered.
+const DEFAULT_FONTS: [(&str, &str); 2] = [("mincho", "ipaexm.ttf"), ("gothic", "ipaexg.ttf")];
+
+/// The fonts embedded in the binary, used for `mincho` & `gothic` when their files are missing.
+#[cfg(feature = "bundled-fonts")]
+const BUNDLED_FONTS: [(&str, &[u8]); 2] = [
+    (
         "mincho",
-        doc.add_external_font(File::open("fonts/ipaexm.ttf")?)?,
-    );
-    fonts.insert(
+        include_bytes!(concat!(env!("OUT_DIR"), "/ipaexm.ttf")),
+    ),
+    (
         "gothic",
-        doc.add_external_font(File::open("fonts/ipaexg.ttf")?)?,
-    );
-    fonts.insert(
+        include_bytes!(concat!(env!("OUT_DIR"), "/ipaexg.ttf")),
+    ),
+];
+
+/// The license of the fonts embedded in the binary.
+#[cfg(feature = "bundled-fonts")]
+pub(crate) const BUNDLED_FONT_LICENSE: &str = include_str!(concat!(
+    env!("OUT_DIR"),
+    "/IPA_Font_License_Agreement_v1.0.txt"
+));
+
+/// The horizontal skew applied to fonts without an italic variant, as `tan(12°)`.
+const OBLIQUE_SKEW: f64 = 0.212_556_56_f64;
+/// The width of the outline drawn around fonts without a bold variant, relative to the font size.
+const SYNTHETIC_BOLD_STROKE: f64 = 0.03_f64;
+/// The suffixes of the variants in a font family, with whether they are bold & italic.
+const VARIANT_SUFFIXES: [(&str, bool, bool); 6] = [
+    ("-BoldItalic", true, true),
+    ("-BoldOblique", true, true),
+    ("-Bold", true, false),
+    ("-Italic", false, true),
+    ("-Oblique", false, true),
+    ("-Roman", false, false),
+];
+/// The fonts that draw the characters missing from fonts without fallbacks of their own,
+/// so Japanese text is drawn even in Latin fonts.
+const DEFAULT_FALLBACKS: [&str; 2] = ["mincho", "gothic"];
+/// The fonts used for bold text in fonts without a bold variant.
+const BOLD_SUBSTITUTES: [(&str, &str); 1] = [("mincho", "gothic")];
+
+/// The 14 fonts every PDF reader has, by their standard names.
+const BUILTIN_FONTS: [(&str, BuiltinFont, &BuiltinMetrics); 14] = [
+    (
         "Times-Roman",
-        doc.add_builtin_font(BuiltinFont::TimesRoman)?,
-    );
-    Ok(fonts)
+        BuiltinFont::TimesRoman,
+        &builtin::TIMES_ROMAN,
+    ),
+    ("Times-Bold", BuiltinFont::TimesBold, &builtin::TIMES_BOLD),
+    (
+        "Times-Italic",
+        BuiltinFont::TimesItalic,
+        &builtin::TIMES_ITALIC,
+    ),
+    (
+        "Times-BoldItalic",
+        BuiltinFont::TimesBoldItalic,
+        &builtin::TIMES_BOLD_ITALIC,
+    ),
+    ("Helvetica", BuiltinFont::Helvetica, &builtin::HELVETICA),
+    (
+        "Helvetica-Bold",
+        BuiltinFont::HelveticaBold,
+        &builtin::HELVETICA_BOLD,
+    ),
+    (
+        "Helvetica-Oblique",
+        BuiltinFont::HelveticaOblique,
+        &builtin::HELVETICA_OBLIQUE,
+    ),
+    (
+        "Helvetica-BoldOblique",
+        BuiltinFont::HelveticaBoldOblique,
+        &builtin::HELVETICA_BOLD_OBLIQUE,
+    ),
+    ("Courier", BuiltinFont::Courier, &builtin::COURIER),
+    (
+        "Courier-Bold",
+        BuiltinFont::CourierBold,
+        &builtin::COURIER_BOLD,
+    ),
+    (
+        "Courier-Oblique",
+        BuiltinFont::CourierOblique,
+        &builtin::COURIER_OBLIQUE,
+    ),
+    (
+        "Courier-BoldOblique",
+        BuiltinFont::CourierBoldOblique,
+        &builtin::COURIER_BOLD_OBLIQUE,
+    ),
+    ("Symbol", BuiltinFont::Symbol, &builtin::SYMBOL),
+    (
+        "ZapfDingbats",
+        BuiltinFont::ZapfDingbats,
+        &builtin::ZAPF_DINGBATS,
+    ),
+];
+
+/// The tables holding PostScript outlines, which fonts with TrueType outlines do not have.
+const CFF_TABLES: [&[u8; 4]; 2] = [b"CFF ", b"CFF2"];
+
+/// The built-in fonts with their own encoding.
+const SYMBOLIC_FONTS: [&[u8]; 2] = [b"Symbol", b"ZapfDingbats"];
+
+/// The `OpenType` features that substitute glyphs for vertical writing.
+const VERTICAL_FEATURES: [&[u8; 4]; 2] = [b"vrt2", b"vert"];
+
+/// The variation selectors that pick a variant of the character before them, such as the
+/// Ideographic Variation Sequences for the one & two dot forms of 辻.
+const VARIATION_SELECTORS: [RangeInclusive<char>; 2] =
+    ['\u{FE00}'..='\u{FE0F}', '\u{E0100}'..='\u{E01EF}'];
+
+/// Whether a character selects a variant of the character before it.
+pub(crate) fn is_variation_selector(c: char) -> bool {
+    VARIATION_SELECTORS.iter().any(|range| range.contains(&c))
+}
+
+/// A glyph of a TrueType font, along with the text it draws.
+struct Glyph<'t> {
+    id: GlyphId,
+    text: &'t str,
+    /// The variation selector the font has no variant for, if any.
+    missing_variant: Option<char>,
+}
+
+/// Maps text to the glyphs of a font, using the variants picked by variation selectors
+/// from the font's format 14 `cmap` subtable. Characters the font does not have are skipped.
+fn shape<'t>(face: &Face<'_>, text: &'t str) -> Vec<Glyph<'t>> {
+    let mut glyphs = Vec::new();
+    let mut chars = text.char_indices().peekable();
+    while let Some((start, c)) = chars.next() {
+        // A selector without a character before it selects nothing
+        if is_variation_selector(c) {
+            continue;
+        }
+        let selector = chars.next_if(|(_, next)| is_variation_selector(*next));
+        let end = selector.map_or(start + c.len_utf8(), |(index, selector)| {
+            index + selector.len_utf8()
+        });
+        let variant = selector.and_then(|(_, selector)| face.glyph_variation_index(c, selector));
+        let Some(id) = variant.or_else(|| face.glyph_index(c)) else {
+            continue;
+        };
+        let missing_variant = selector
+            .filter(|_| variant.is_none())
+            .map(|(_, selector)| selector);
+        // The default glyph only stands for the character itself
+        let end = if missing_variant.is_some() {
+            start + c.len_utf8()
+        } else {
+            end
+        };
+        glyphs.push(Glyph {
+            id,
+            text: text.get(start..end).unwrap_or_default(),
+            missing_variant,
+        });
+    }
+    glyphs
+}
+
+/// The metrics needed to measure text set in a font.
+enum Metrics {
+    /// A TrueType font.
+    TrueType(ParsedFace),
+    /// The metrics of a built-in font.
+    Builtin(&'static BuiltinMetrics),
+}
+
+/// A font added to the PDF document.
+pub(crate) struct LoadedFont {
+    pub(crate) reference: IndirectFontRef,
+    /// The name the font is used by in the style file.
+    name: String,
+    metrics: Metrics,
+    /// The text each glyph drawn so far stands for, to map the glyphs back to text.
+    drawn: RefCell<BTreeMap<u16, String>>,
+}
+
+impl LoadedFont {
+    fn external(doc: &PdfDocumentReference, name: &str, path: &Path) -> Result<Self> {
+        let data =
+            read(path).with_context(|| format!("Failed to read the font {}", path.display()))?;
+        let face = ParsedFace::try_new(data, |data| Face::parse(data, 0))
+            .with_context(|| format!("Failed to read the font {}", path.display()))?;
+        // Only TrueType outlines are embedded, so PostScript outlines would not be drawn
+        if CFF_TABLES.iter().any(|tag| {
+            face.borrow_dependent()
+                .raw_face()
+                .table(Tag::from_bytes(tag))
+                .is_some()
+        }) {
+            return Err(anyhow!(
+                "The font {} has PostScript (CFF) outlines, which cannot be embedded. \
+                 Use a font with TrueType outlines, usually a .ttf file",
+                path.display()
+            ));
+        }
+        Self::from_face(doc, name, face)
+    }
+
+    fn from_data(doc: &PdfDocumentReference, name: &str, data: Vec<u8>) -> Result<Self> {
+        let face = ParsedFace::try_new(data, |data| Face::parse(data, 0))
+            .with_context(|| format!("Failed to read the font for {name}"))?;
+        Self::from_face(doc, name, face)
+    }
+
+    fn from_face(doc: &PdfDocumentReference, name: &str, face: ParsedFace) -> Result<Self> {
+        let reference = doc.add_external_font(face.borrow_owner().as_slice())?;
+        Ok(LoadedFont {
+            reference,
+            name: name.to_owned(),
+            metrics: Metrics::TrueType(face),
+            drawn: RefCell::new(BTreeMap::new()),
+        })
+    }
+
+    fn builtin(
+        doc: &PdfDocumentReference,
+        name: &str,
+        font: BuiltinFont,
+        metrics: &'static BuiltinMetrics,
+    ) -> Result<Self> {
+        Ok(LoadedFont {
+            reference: doc.add_builtin_font(font)?,
+            name: name.to_owned(),
+            metrics: Metrics::Builtin(metrics),
+            drawn: RefCell::new(BTreeMap::new()),
+        })
+    }
+
+    /// Whether the font has a glyph for a character, in the variant picked by the selector.
+    /// Built-in fonts cover the characters of their own encoding, without any variants.
+    fn covers(&self, c: char, selector: Option<char>) -> bool {
+        match &self.metrics {
+            Metrics::TrueType(face) => match selector {
+                Some(selector) => face
+                    .borrow_dependent()
+                    .glyph_variation_index(c, selector)
+                    .is_some(),
+                None => face.borrow_dependent().glyph_index(c).is_some(),
+            },
+            Metrics::Builtin(metrics) => selector.is_none() && metrics.encode(c).is_some(),
+        }
+    }
+
+    /// Writes text in a text section that uses this font.
+    pub(crate) fn write(&self, text: &str, layer: &PdfLayerReference) {
+        self.write_glyphs(text, false, layer);
+    }
+
+    /// Writes text in a text section that uses this font, with the glyphs for vertical writing.
+    /// Built-in fonts only have glyphs for horizontal writing.
+    pub(crate) fn write_vertical(&self, text: &str, layer: &PdfLayerReference) {
+        self.write_glyphs(text, true, layer);
+    }
+
+    /// Text in TrueType fonts is written as glyphs, which are remembered to map them back to text.
+    /// Text in built-in fonts is written in the font's own encoding, as printpdf only knows
+    /// `WinAnsi`.
+    fn write_glyphs(&self, text: &str, vertical: bool, layer: &PdfLayerReference) {
+        let face = match &self.metrics {
+            Metrics::TrueType(face) => face.borrow_dependent(),
+            Metrics::Builtin(metrics) => {
+                let codes = text.chars().filter_map(|c| metrics.encode(c)).collect();
+                layer.add_operation(Operation::new(
+                    "Tj",
+                    vec![Object::String(codes, StringFormat::Literal)],
+                ));
+                return;
+            }
+        };
+        let mut glyphs = shape(face, text);
+        if vertical {
+            for glyph in &mut glyphs {
+                glyph.id = substitute_vertical(face, glyph.id);
+            }
+        }
+        let mut drawn = self.drawn.borrow_mut();
+        for glyph in &glyphs {
+            if let Some(selector) = glyph.missing_variant {
+                warn(&format!(
+                    "The font {} has no variant of {} for the selector U+{:04X}, drawing its \
+                     default glyph instead",
+                    self.name,
+                    glyph.text.chars().next().unwrap_or_default(),
+                    u32::from(selector)
+                ));
+            }
+            let _first = drawn
+                .entry(glyph.id.0)
+                .or_insert_with(|| glyph.text.to_owned());
+        }
+        layer.write_codepoints(glyphs.iter().map(|glyph| glyph.id.0));
+    }
+
+    /// The reference to a TrueType font, along with the text each glyph drawn stands for.
+    fn into_drawn_text(self) -> Option<(IndirectFontRef, BTreeMap<u16, String>)> {
+        match self.metrics {
+            Metrics::TrueType(_) => Some((self.reference, self.drawn.into_inner())),
+            Metrics::Builtin(_) => None,
+        }
+    }
+
+    /// Measures the width of a single line of text.
+    pub(crate) fn text_width(&self, text: &str, font_size: f64) -> Mm {
+        match &self.metrics {
+            Metrics::TrueType(face) => {
+                let face = face.borrow_dependent();
+                let advance: u32 = shape(face, text)
+                    .into_iter()
+                    .filter_map(|glyph| face.glyph_hor_advance(glyph.id))
+                    .map(u32::from)
+                    .sum();
+                face_units_to_mm(face, f64::from(advance), font_size)
+            }
+            Metrics::Builtin(metrics) => {
+                let advance: u32 = text
+                    .chars()
+                    .filter_map(|c| metrics.width(metrics.encode(c)?))
+                    .map(u32::from)
+                    .sum();
+                builtin_units_to_mm(f64::from(advance), font_size)
+            }
+        }
+    }
+
+    /// The distance from the baseline to the top of the font.
+    pub(crate) fn ascent(&self, font_size: f64) -> Mm {
+        match &self.metrics {
+            Metrics::TrueType(face) => {
+                let face = face.borrow_dependent();
+                face_units_to_mm(face, f64::from(face.ascender()), font_size)
+            }
+            Metrics::Builtin(metrics) => builtin_units_to_mm(f64::from(metrics.ascent), font_size),
+        }
+    }
+
+    /// The distance from the baseline to the bottom of the font, as a negative length.
+    pub(crate) fn descent(&self, font_size: f64) -> Mm {
+        match &self.metrics {
+            Metrics::TrueType(face) => {
+                let face = face.borrow_dependent();
+                face_units_to_mm(face, f64::from(face.descender()), font_size)
+            }
+            Metrics::Builtin(metrics) => builtin_units_to_mm(f64::from(metrics.descent), font_size),
+        }
+    }
+
+    /// The extra space the font asks for between lines, below the descent.
+    /// Built-in fonts have none.
+    pub(crate) fn line_gap(&self, font_size: f64) -> Mm {
+        match &self.metrics {
+            Metrics::TrueType(face) => {
+                let face = face.borrow_dependent();
+                face_units_to_mm(face, f64::from(face.line_gap()), font_size)
+            }
+            Metrics::Builtin(_) => Mm(0.0_f64),
+        }
+    }
+
+    /// The adjustment to the space between two characters from the font's `kern` table,
+    /// negative when they are moved closer together.
+    /// Built-in fonts & characters the font does not have are never kerned.
+    pub(crate) fn kerning(&self, left: char, right: char, font_size: f64) -> Mm {
+        let Metrics::TrueType(face) = &self.metrics else {
+            return Mm(0.0_f64);
+        };
+        let face = face.borrow_dependent();
+        let (Some(left), Some(right)) = (face.glyph_index(left), face.glyph_index(right)) else {
+            return Mm(0.0_f64);
+        };
+        let adjustment = face
+            .tables()
+            .kern
+            .into_iter()
+            .flat_map(|table| table.subtables)
+            .filter(|subtable| {
+                subtable.horizontal && !subtable.variable && !subtable.has_cross_stream
+            })
+            .find_map(|subtable| subtable.glyphs_kerning(left, right))
+            .unwrap_or_default();
+        face_units_to_mm(face, f64::from(adjustment), font_size)
+    }
+}
+
+/// The measurements of a line of text set in a font face.
+#[derive(Clone, Copy)]
+pub(crate) struct TextMetrics {
+    /// How far the text moves along the line, including the letter spacing after every
+    /// character, without kerning as text is drawn unkerned.
+    pub(crate) advance: Mm,
+    /// The distance from the baseline to the top of the font.
+    pub(crate) ascent: Mm,
+    /// The distance from the baseline to the bottom of the font, as a negative length.
+    pub(crate) descent: Mm,
+    /// The extra space the font asks for between lines.
+    #[allow(
+        dead_code,
+        reason = "lines are spaced by the line height, so the gap is only there to be queried"
+    )]
+    pub(crate) line_gap: Mm,
+}
+
+/// Converts a length in the units of a TrueType font to millimetres at the given font size.
+fn face_units_to_mm(face: &Face<'_>, units: f64, font_size: f64) -> Mm {
+    Mm::from(Pt(units / f64::from(face.units_per_em()) * font_size))
+}
+
+/// Converts a length in the units of a built-in font to millimetres at the given font size.
+fn builtin_units_to_mm(units: f64, font_size: f64) -> Mm {
+    Mm::from(Pt(units / 1000.0_f64 * font_size))
+}
+
+/// Applies the vertical writing substitutions in the font's `GSUB` table to a glyph.
+fn substitute_vertical(face: &Face<'_>, glyph: GlyphId) -> GlyphId {
+    let Some(gsub) = face.tables().gsub else {
+        return glyph;
+    };
+    let Some(feature) = VERTICAL_FEATURES
+        .iter()
+        .find_map(|tag| gsub.features.find(Tag::from_bytes(tag)))
+    else {
+        return glyph;
+    };
+    for lookup_index in feature.lookup_indices {
+        let Some(lookup) = gsub.lookups.get(lookup_index) else {
+            continue;
+        };
+        for subtable in lookup.subtables.into_iter::<SubstitutionSubtable<'_>>() {
+            let SubstitutionSubtable::Single(single) = subtable else {
+                continue;
+            };
+            let Some(coverage_index) = single.coverage().get(glyph) else {
+                continue;
+            };
+            let substitute = match single {
+                SingleSubstitution::Format1 { delta, .. } => {
+                    Some(GlyphId(glyph.0.wrapping_add_signed(delta)))
+                }
+                SingleSubstitution::Format2 { substitutes, .. } => substitutes.get(coverage_index),
+            };
+            if let Some(substitute) = substitute {
+                return substitute;
+            }
+        }
+    }
+    glyph
+}
+
+/// Where a font is loaded from.
+enum FontSource {
+    /// A font file that was found already.
+    File(PathBuf),
+    /// A default font, which is only looked for once it is used.
+    Default(Font),
+    Builtin(BuiltinFont, &'static BuiltinMetrics),
+}
+
+/// A font that is added to the document the first time it is used.
+struct FontSlot {
+    source: FontSource,
+    loaded: OnceCell<LoadedFont>,
+}
+
+impl FontSlot {
+    fn new(source: FontSource) -> Self {
+        FontSlot {
+            source,
+            loaded: OnceCell::new(),
+        }
+    }
+}
+
+/// The fonts that can be drawn with by name, along with the fallbacks of each font face.
+pub(crate) struct FontMap<'a> {
+    doc: &'a PdfDocumentReference,
+    registry: &'a FontRegistry,
+    fonts: HashMap<&'a str, FontSlot>,
+    fallbacks: HashMap<&'a str, Vec<&'a str>>,
 }
 
-pub(crate) fn handle_font<'a>(
-    name: &'a String,
+impl FontMap<'_> {
+    /// The font by this name, if there is one, which is added to the document the first time.
+    fn get(&self, name: &str) -> Result<Option<&LoadedFont>> {
+        let Some((name, slot)) = self.fonts.get_key_value(name) else {
+            return Ok(None);
+        };
+        if let Some(font) = slot.loaded.get() {
+            return Ok(Some(font));
+        }
+        let font = match &slot.source {
+            FontSource::File(path) => LoadedFont::external(self.doc, name, path)?,
+            FontSource::Default(font) => match self.registry.locate(font) {
+                Ok(path) => LoadedFont::external(self.doc, name, &path)?,
+                Err(error) => match bundled_font(name) {
+                    Some(data) => LoadedFont::from_data(self.doc, name, data.to_vec())?,
+                    None => return Err(error),
+                },
+            },
+            FontSource::Builtin(font, metrics) => {
+                LoadedFont::builtin(self.doc, name, *font, metrics)?
+            }
+        };
+        Ok(Some(slot.loaded.get_or_init(|| font)))
+    }
+
+    /// Whether there is a font by this name.
+    pub(crate) fn contains(&self, name: &str) -> bool {
+        self.fonts.contains_key(name)
+    }
+
+    /// The text the glyphs drawn with each TrueType font stand for, by the reference to the
+    /// font, which outlive the document the fonts were added to.
+    pub(crate) fn into_drawn_text(self) -> BTreeMap<IndirectFontRef, BTreeMap<u16, String>> {
+        self.fonts
+            .into_values()
+            .filter_map(|slot| slot.loaded.into_inner())
+            .filter_map(LoadedFont::into_drawn_text)
+            .collect()
+    }
+
+    /// Measures a single line of plain text as it would be drawn in the requested face.
+    /// The vertical metrics are those of the main font of the face, not its fallbacks.
+    pub(crate) fn measure(
+        &self,
+        text: &str,
+        request: FaceRequest<'_>,
+        font_size: f64,
+    ) -> Result<TextMetrics> {
+        resolve_typeface(request, self)?.measure(text, font_size)
+    }
+
+    /// The kerning between two characters set in the requested face, which is zero when they
+    /// are drawn with different fonts.
+    #[allow(
+        dead_code,
+        reason = "text is drawn unkerned, so kerning is only there to be queried"
+    )]
+    pub(crate) fn kerning(
+        &self,
+        left: char,
+        right: char,
+        request: FaceRequest<'_>,
+        font_size: f64,
+    ) -> Result<Mm> {
+        Ok(resolve_typeface(request, self)?.kerning(left, right, font_size))
+    }
+
+    /// The font faces to draw the characters missing from a face with, in order,
+    /// along with whether they are the default ones.
+    fn fallbacks(&self, face: &str) -> (Vec<&str>, bool) {
+        let (family, _, _) = split_variant(face);
+        self.fallbacks
+            .get(face)
+            .or_else(|| self.fallbacks.get(family))
+            .map_or_else(
+                || (DEFAULT_FALLBACKS.to_vec(), true),
+                |faces| (faces.clone(), false),
+            )
+    }
+}
+
+/// The font embedded in the binary for a default font.
+#[cfg(feature = "bundled-fonts")]
+fn bundled_font(alias: &str) -> Option<&'static [u8]> {
+    BUNDLED_FONTS
+        .iter()
+        .find(|(name, _)| *name == alias)
+        .map(|(_, data)| *data)
+}
+
+/// The font embedded in the binary for a default font, which is never found without the
+/// `bundled-fonts` feature.
+#[cfg(not(feature = "bundled-fonts"))]
+fn bundled_font(_alias: &str) -> Option<&'static [u8]> {
+    None
+}
+
+/// Removes the `WinAnsi` encoding printpdf gives every built-in font from `Symbol` &
+/// `ZapfDingbats`, so their text is read in their own encoding.
+pub(crate) fn use_builtin_encodings(document: &mut Document) {
+    for object in document.objects.values_mut() {
+        let Ok(dictionary) = object.as_dict_mut() else {
+            continue;
+        };
+        let symbolic = dictionary
+            .get(b"BaseFont")
+            .and_then(Object::as_name)
+            .is_ok_and(|name| SYMBOLIC_FONTS.contains(&name));
+        if symbolic {
+            let _encoding = dictionary.remove(b"Encoding");
+        }
+    }
+}
+
+/// Collects the fonts that can be drawn with, which are only added to the document once used.
+/// Registered fonts are looked for up front, so a missing file is reported even if unused.
+#[allow(unused_results, reason = "no font is registered twice")]
+pub(crate) fn get_fonts<'a>(
+    doc: &'a PdfDocumentReference,
+    registry: &'a FontRegistry,
+) -> Result<FontMap<'a>> {
+    let mut fonts = HashMap::new();
+    for (alias, file_name) in DEFAULT_FONTS {
+        let font = Font {
+            alias: alias.to_owned(),
+            path: file_name.to_owned(),
+            directory: PathBuf::new(),
+        };
+        fonts.insert(alias, FontSlot::new(FontSource::Default(font)));
+    }
+    for (name, font, metrics) in BUILTIN_FONTS {
+        fonts.insert(name, FontSlot::new(FontSource::Builtin(font, metrics)));
+    }
+    // Registered fonts can replace the default & built-in ones
+    for font in registry.fonts() {
+        let path = registry.locate(font)?;
+        fonts.insert(font.alias.as_str(), FontSlot::new(FontSource::File(path)));
+    }
+    let fallbacks = registry
+        .fallbacks()
+        .iter()
+        .map(|fallback| {
+            let faces = fallback.fallbacks.iter().map(String::as_str).collect();
+            (fallback.face.as_str(), faces)
+        })
+        .collect();
+    Ok(FontMap {
+        doc,
+        registry,
+        fonts,
+        fallbacks,
+    })
+}
+
+/// A font ready to draw text with.
+pub(crate) struct Typeface<'a> {
+    pub(crate) font: &'a LoadedFont,
+    /// Whether to slant the font, for italics in fonts without an italic variant.
+    pub(crate) oblique: bool,
+    /// Whether to thicken the font, for bold text in fonts without a bold variant.
+    pub(crate) emboldened: bool,
+    /// The extra space after each character.
+    pub(crate) letter_spacing: Mm,
+    /// The typefaces drawing the characters the font has no glyphs for, in order.
+    pub(crate) fallbacks: Vec<Typeface<'a>>,
+}
+
+impl<'a> Typeface<'a> {
+    /// This typeface with `extra` space after each character, in its fallbacks too.
+    pub(crate) fn spaced(&self, extra: Mm) -> Self {
+        Typeface {
+            font: self.font,
+            oblique: self.oblique,
+            emboldened: self.emboldened,
+            letter_spacing: self.letter_spacing + extra,
+            fallbacks: self
+                .fallbacks
+                .iter()
+                .map(|fallback| fallback.spaced(extra))
+                .collect(),
+        }
+    }
+
+    /// Measures a single line of plain text as it would be drawn in this typeface.
+    /// The vertical metrics are those of the main font, not its fallbacks.
+    pub(crate) fn measure(&self, text: &str, font_size: f64) -> Result<TextMetrics> {
+        Ok(TextMetrics {
+            advance: self.text_width(text, font_size)?,
+            ascent: self.font.ascent(font_size),
+            descent: self.font.descent(font_size),
+            line_gap: self.font.line_gap(font_size),
+        })
+    }
+
+    /// The kerning between two characters, which is zero when they are drawn with different
+    /// fonts of the fallback chain.
+    pub(crate) fn kerning(&self, left: char, right: char, font_size: f64) -> Mm {
+        let font = self.for_sequence(left, None).font;
+        if ptr::eq(font, self.for_sequence(right, None).font) {
+            font.kerning(left, right, font_size)
+        } else {
+            Mm(0.0_f64)
+        }
+    }
+
+    /// Picks the first typeface of the fallback chain with a glyph for a character, preferring
+    /// the ones with the variant picked by a variation selector.
+    /// Characters no font covers are left to the main font.
+    fn for_sequence(&self, c: char, selector: Option<char>) -> &Typeface<'a> {
+        let chain = || iter::once(self).chain(&self.fallbacks);
+        selector
+            .and_then(|selector| chain().find(|typeface| typeface.font.covers(c, Some(selector))))
+            .or_else(|| chain().find(|typeface| typeface.font.covers(c, None)))
+            .unwrap_or(self)
+    }
+
+    /// Picks the typeface to draw a single character with, which may be followed by a
+    /// variation selector.
+    pub(crate) fn for_cell(&self, cell: &str) -> &Typeface<'a> {
+        let mut chars = cell.chars();
+        match chars.next() {
+            Some(c) => {
+                self.for_sequence(c, chars.next().filter(|next| is_variation_selector(*next)))
+            }
+            None => self,
+        }
+    }
+
+    /// Splits text into runs of characters drawn with the same typeface.
+    /// Variation selectors stay with the character before them.
+    fn runs<'t>(&'t self, text: &'t str) -> Vec<(&'t Typeface<'a>, &'t str)> {
+        let mut runs = Vec::new();
+        let mut current: Option<(&Typeface<'a>, usize)> = None;
+        let mut chars = text.char_indices().peekable();
+        while let Some((index, c)) = chars.next() {
+            if is_variation_selector(c) {
+                continue;
+            }
+            let selector = chars
+                .peek()
+                .map(|(_, next)| *next)
+                .filter(|next| is_variation_selector(*next));
+            let typeface = self.for_sequence(c, selector);
+            match current {
+                Some((previous, _)) if ptr::eq(previous, typeface) => {}
+                Some((previous, start)) => {
+                    runs.push((previous, text.get(start..index).unwrap_or_default()));
+                    current = Some((typeface, index));
+                }
+                None => current = Some((typeface, index)),
+            }
+        }
+        if let Some((typeface, start)) = current {
+            runs.push((typeface, text.get(start..).unwrap_or_default()));
+        }
+        runs
+    }
+
+    /// Measures the width of a single line of text, set in the fonts of the fallback chain.
+    /// The letter spacing is counted after every character, including the last.
+    pub(crate) fn text_width(&self, text: &str, font_size: f64) -> Result<Mm> {
+        let mut width = Mm(0.0_f64);
+        for (typeface, run) in self.runs(text) {
+            width += typeface.run_width(run, font_size)?;
+        }
+        Ok(width)
+    }
+
+    /// Measures the width of text drawn in this typeface's font only.
+    fn run_width(&self, text: &str, font_size: f64) -> Result<Mm> {
+        let characters = text.chars().filter(|c| !is_variation_selector(*c)).count();
+        Ok(self.font.text_width(text, font_size)
+            + self.letter_spacing * f64::from(u32::try_from(characters)?))
+    }
+
+    /// Draws text with its baseline starting at `x` & `y`,
+    /// switching to the fallback fonts for the characters missing from the font.
+    pub(crate) fn draw(
+        &self,
+        text: &str,
+        font_size: f64,
+        x: Mm,
+        y: Mm,
+        layer: &PdfLayerReference,
+    ) -> Result<()> {
+        let mut x = x;
+        for (typeface, run) in self.runs(text) {
+            typeface.draw_run(run, font_size, x, y, layer);
+            x += typeface.run_width(run, font_size)?;
+        }
+        Ok(())
+    }
+
+    /// Draws text in this typeface's font only.
+    fn draw_run(&self, text: &str, font_size: f64, x: Mm, y: Mm, layer: &PdfLayerReference) {
+        if self.emboldened {
+            layer.save_graphics_state();
+        }
+        layer.begin_text_section();
+        layer.set_font(&self.font.reference, font_size);
+        if self.emboldened {
+            layer.set_text_rendering_mode(TextRenderingMode::FillStroke);
+            layer.set_outline_thickness(font_size * SYNTHETIC_BOLD_STROKE);
+        }
+        if self.oblique || self.emboldened {
+            let skew = if self.oblique { OBLIQUE_SKEW } else { 0.0_f64 };
+            layer.set_text_matrix(TextMatrix::Raw([
+                1.0_f64,
+                0.0_f64,
+                skew,
+                1.0_f64,
+                Pt::from(x).0,
+                Pt::from(y).0,
+            ]));
+        } else {
+            layer.set_text_cursor(x, y);
+        }
+        let spaced = self.letter_spacing.0 != 0.0_f64;
+        if spaced {
+            layer.set_character_spacing(Pt::from(self.letter_spacing).0);
+        }
+        self.font.write(text, layer);
+        if spaced {
+            // The spacing is part of the graphics state, so it would carry over to later text
+            layer.set_character_spacing(0.0_f64);
+        }
+        layer.end_text_section();
+        if self.emboldened {
+            layer.restore_graphics_state();
+        }
+    }
+}
+
+/// A font face along with the weight & style to draw it in.
+#[derive(Clone, Copy)]
+pub(crate) struct FaceRequest<'a> {
+    pub(crate) face: &'a str,
+    pub(crate) bold: bool,
+    pub(crate) italic: bool,
+    /// Whether to thicken fonts that have no bold variant.
+    pub(crate) synthetic_bold: bool,
+    /// The extra space after each character.
+    pub(crate) letter_spacing: Mm,
+}
+
+impl<'a> FaceRequest<'a> {
+    pub(crate) fn new(options: &'a FontOptions) -> Self {
+        FaceRequest {
+            face: options.font_face.as_deref().unwrap_or(DEFAULT_FONT_FACE),
+            bold: options.font_weight == Some(FontWeight::Bold),
+            italic: options.font_style == Some(FontStyle::Italic),
+            synthetic_bold: options.synthetic_bold.unwrap_or_default(),
+            letter_spacing: options.letter_spacing.unwrap_or(Mm(0.0_f64)),
+        }
+    }
+}
+
+/// Splits a font name such as `Times-BoldItalic` into its family & whether it is bold & italic.
+fn split_variant(face: &str) -> (&str, bool, bool) {
+    VARIANT_SUFFIXES
+        .iter()
+        .find_map(|(suffix, bold, italic)| {
+            face.strip_suffix(suffix)
+                .map(|family| (family, *bold, *italic))
+        })
+        .unwrap_or((face, false, false))
+}
+
+/// Finds a variant of a font family, named with the standard suffixes.
+fn find_variant<'a>(
+    family: &str,
+    bold: bool,
+    italic: bool,
+    fonts: &'a FontMap<'a>,
+) -> Result<Option<&'a LoadedFont>> {
+    let suffixes: &[&str] = match (bold, italic) {
+        (true, true) => &["-BoldItalic", "-BoldOblique"],
+        (true, false) => &["-Bold"],
+        (false, true) => &["-Italic", "-Oblique"],
+        (false, false) => &["", "-Roman"],
+    };
+    for suffix in suffixes {
+        if let Some(font) = fonts.get(format!("{family}{suffix}").as_str())? {
+            return Ok(Some(font));
+        }
+    }
+    Ok(None)
+}
+
+/// Picks the fonts to draw a face in the requested weight & style with,
+/// followed by the fonts of its fallbacks in the same weight & style.
+pub(crate) fn resolve_typeface<'a>(
+    request: FaceRequest<'_>,
     fonts: &'a FontMap<'a>,
-) -> Result<&'a IndirectFontRef> {
-    if let Some(font) = fonts.get(name.as_str()) {
+) -> Result<Typeface<'a>> {
+    let mut typeface = resolve_face(request, fonts)?;
+    let (faces, default) = fonts.fallbacks(request.face);
+    for face in faces {
+        let fallback = match resolve_face(FaceRequest { face, ..request }, fonts) {
+            Ok(fallback) => fallback,
+            // The default fallbacks are only used when their fonts are found
+            Err(_) if default => continue,
+            Err(error) => return Err(error),
+        };
+        let known = ptr::eq(fallback.font, typeface.font)
+            || typeface
+                .fallbacks
+                .iter()
+                .any(|existing| ptr::eq(fallback.font, existing.font));
+        if !known {
+            typeface.fallbacks.push(fallback);
+        }
+    }
+    Ok(typeface)
+}
+
+/// Picks the font to draw a face in the requested weight & style with.
+/// Missing italics are slanted, while missing bold is set in a substitute such as gothic for
+/// mincho, or thickened if synthetic bold is enabled.
+fn resolve_face<'a>(request: FaceRequest<'_>, fonts: &'a FontMap<'a>) -> Result<Typeface<'a>> {
+    let (family, face_bold, face_italic) = split_variant(request.face);
+    let bold = request.bold || face_bold;
+    let italic = request.italic || face_italic;
+    if let Some(font) = find_variant(family, bold, italic, fonts)? {
+        return Ok(Typeface {
+            font,
+            oblique: false,
+            emboldened: false,
+            letter_spacing: request.letter_spacing,
+            fallbacks: Vec::new(),
+        });
+    }
+    if bold {
+        if let Some(font) = find_variant(family, true, false, fonts)? {
+            return Ok(Typeface {
+                font,
+                oblique: italic,
+                emboldened: false,
+                letter_spacing: request.letter_spacing,
+                fallbacks: Vec::new(),
+            });
+        }
+        if let Some((_, substitute)) = BOLD_SUBSTITUTES.iter().find(|(face, _)| *face == family) {
+            let typeface = resolve_face(
+                FaceRequest {
+                    face: substitute,
+                    bold: false,
+                    ..request
+                },
+                fonts,
+            )?;
+            return Ok(Typeface {
+                emboldened: request.synthetic_bold,
+                ..typeface
+            });
+        }
+    }
+    let (font, oblique) = match find_variant(family, false, italic, fonts)? {
+        Some(font) => (font, false),
+        None => (handle_font(request.face, fonts)?, italic),
+    };
+    Ok(Typeface {
+        font,
+        oblique,
+        emboldened: bold && request.synthetic_bold,
+        letter_spacing: request.letter_spacing,
+        fallbacks: Vec::new(),
+    })
+}
+
+pub(crate) fn handle_font<'a>(name: &str, fonts: &'a FontMap<'a>) -> Result<&'a LoadedFont> {
+    if let Some(font) = fonts.get(name)? {
         Ok(font)
     } else {
         Err(anyhow!("Failed to fetch font: {}", name))
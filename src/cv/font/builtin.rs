@@ -0,0 +1,602 @@
+//! Metrics & encodings of the built-in PDF fonts, taken from the Adobe Core 14 AFM files.
+
+use printpdf::lopdf::Document;
+
+/// How the characters drawn with a built-in font are turned into its codes.
+pub(super) enum BuiltinEncoding {
+    WinAnsi,
+    /// The font's own encoding of Greek letters & mathematical symbols.
+    Symbol,
+    /// The font's own encoding, which the Unicode Dingbats block was made from.
+    ZapfDingbats,
+}
+
+/// The metrics of a built-in font in thousandths of an em.
+pub(super) struct BuiltinMetrics {
+    pub(super) ascent: i16,
+    pub(super) descent: i16,
+    /// The glyph widths for the character codes 32 to 255 of the font's encoding.
+    pub(super) widths: [u16; 224],
+    pub(super) encoding: BuiltinEncoding,
+}
+
+impl BuiltinMetrics {
+    /// The code of a character in the font's encoding, if the font has a glyph for it.
+    pub(super) fn encode(&self, c: char) -> Option<u8> {
+        let code = match self.encoding {
+            BuiltinEncoding::WinAnsi => {
+                let encoded =
+                    Document::encode_text(Some("WinAnsiEncoding"), c.encode_utf8(&mut [0; 4]));
+                encoded.first().copied()
+            }
+            BuiltinEncoding::Symbol => SYMBOL_ENCODING
+                .binary_search_by_key(&c, |(character, _)| *character)
+                .ok()
+                .and_then(|index| SYMBOL_ENCODING.get(index))
+                .map(|(_, code)| *code),
+            BuiltinEncoding::ZapfDingbats => zapf_dingbats_code(c),
+        }?;
+        self.width(code).filter(|width| *width > 0).map(|_| code)
+    }
+
+    /// The width of the glyph for a code of the font's encoding.
+    pub(super) fn width(&self, code: u8) -> Option<u16> {
+        self.widths.get(usize::from(code).checked_sub(32)?).copied()
+    }
+}
+
+/// The characters of `Symbol` & their codes, ordered by character.
+const SYMBOL_ENCODING: [(char, u8); 184] = [
+    ('\u{0020}', 0x20),
+    ('\u{0021}', 0x21),
+    ('\u{0023}', 0x23),
+    ('\u{0025}', 0x25),
+    ('\u{0026}', 0x26),
+    ('\u{0028}', 0x28),
+    ('\u{0029}', 0x29),
+    ('\u{002B}', 0x2B),
+    ('\u{002C}', 0x2C),
+    ('\u{002E}', 0x2E),
+    ('\u{002F}', 0x2F),
+    ('\u{0030}', 0x30),
+    ('\u{0031}', 0x31),
+    ('\u{0032}', 0x32),
+    ('\u{0033}', 0x33),
+    ('\u{0034}', 0x34),
+    ('\u{0035}', 0x35),
+    ('\u{0036}', 0x36),
+    ('\u{0037}', 0x37),
+    ('\u{0038}', 0x38),
+    ('\u{0039}', 0x39),
+    ('\u{003A}', 0x3A),
+    ('\u{003B}', 0x3B),
+    ('\u{003C}', 0x3C),
+    ('\u{003D}', 0x3D),
+    ('\u{003E}', 0x3E),
+    ('\u{003F}', 0x3F),
+    ('\u{005B}', 0x5B),
+    ('\u{005D}', 0x5D),
+    ('\u{005F}', 0x5F),
+    ('\u{007B}', 0x7B),
+    ('\u{007C}', 0x7C),
+    ('\u{007D}', 0x7D),
+    ('\u{00AC}', 0xD8),
+    ('\u{00B0}', 0xB0),
+    ('\u{00B1}', 0xB1),
+    ('\u{00B5}', 0x6D),
+    ('\u{00D7}', 0xB4),
+    ('\u{00F7}', 0xB8),
+    ('\u{0192}', 0xA6),
+    ('\u{0391}', 0x41),
+    ('\u{0392}', 0x42),
+    ('\u{0393}', 0x47),
+    ('\u{0395}', 0x45),
+    ('\u{0396}', 0x5A),
+    ('\u{0397}', 0x48),
+    ('\u{0398}', 0x51),
+    ('\u{0399}', 0x49),
+    ('\u{039A}', 0x4B),
+    ('\u{039B}', 0x4C),
+    ('\u{039C}', 0x4D),
+    ('\u{039D}', 0x4E),
+    ('\u{039E}', 0x58),
+    ('\u{039F}', 0x4F),
+    ('\u{03A0}', 0x50),
+    ('\u{03A1}', 0x52),
+    ('\u{03A3}', 0x53),
+    ('\u{03A4}', 0x54),
+    ('\u{03A5}', 0x55),
+    ('\u{03A6}', 0x46),
+    ('\u{03A7}', 0x43),
+    ('\u{03A8}', 0x59),
+    ('\u{03B1}', 0x61),
+    ('\u{03B2}', 0x62),
+    ('\u{03B3}', 0x67),
+    ('\u{03B4}', 0x64),
+    ('\u{03B5}', 0x65),
+    ('\u{03B6}', 0x7A),
+    ('\u{03B7}', 0x68),
+    ('\u{03B8}', 0x71),
+    ('\u{03B9}', 0x69),
+    ('\u{03BA}', 0x6B),
+    ('\u{03BB}', 0x6C),
+    ('\u{03BD}', 0x6E),
+    ('\u{03BE}', 0x78),
+    ('\u{03BF}', 0x6F),
+    ('\u{03C0}', 0x70),
+    ('\u{03C1}', 0x72),
+    ('\u{03C2}', 0x56),
+    ('\u{03C3}', 0x73),
+    ('\u{03C4}', 0x74),
+    ('\u{03C5}', 0x75),
+    ('\u{03C6}', 0x66),
+    ('\u{03C7}', 0x63),
+    ('\u{03C8}', 0x79),
+    ('\u{03C9}', 0x77),
+    ('\u{03D1}', 0x4A),
+    ('\u{03D2}', 0xA1),
+    ('\u{03D5}', 0x6A),
+    ('\u{03D6}', 0x76),
+    ('\u{2016}', 0xFD),
+    ('\u{2022}', 0xB7),
+    ('\u{2026}', 0xBC),
+    ('\u{2032}', 0xA2),
+    ('\u{2033}', 0xB2),
+    ('\u{2044}', 0xA4),
+    ('\u{2111}', 0xC1),
+    ('\u{2118}', 0xC3),
+    ('\u{211C}', 0xC2),
+    ('\u{2126}', 0x57),
+    ('\u{2135}', 0xC0),
+    ('\u{2190}', 0xAC),
+    ('\u{2191}', 0xAD),
+    ('\u{2192}', 0xAE),
+    ('\u{2193}', 0xAF),
+    ('\u{2194}', 0xAB),
+    ('\u{2195}', 0xBD),
+    ('\u{21B5}', 0xBF),
+    ('\u{21D0}', 0xDC),
+    ('\u{21D1}', 0xDD),
+    ('\u{21D2}', 0xDE),
+    ('\u{21D3}', 0xDF),
+    ('\u{21D4}', 0xDB),
+    ('\u{2200}', 0x22),
+    ('\u{2202}', 0xB6),
+    ('\u{2203}', 0x24),
+    ('\u{2205}', 0xC6),
+    ('\u{2206}', 0x44),
+    ('\u{2207}', 0xD1),
+    ('\u{2208}', 0xCE),
+    ('\u{2209}', 0xCF),
+    ('\u{220B}', 0x27),
+    ('\u{220F}', 0xD5),
+    ('\u{2211}', 0xE5),
+    ('\u{2212}', 0x2D),
+    ('\u{2217}', 0x2A),
+    ('\u{221A}', 0xD6),
+    ('\u{221D}', 0xB5),
+    ('\u{221E}', 0xA5),
+    ('\u{2220}', 0xD0),
+    ('\u{2227}', 0xD9),
+    ('\u{2228}', 0xDA),
+    ('\u{2229}', 0xC7),
+    ('\u{222A}', 0xC8),
+    ('\u{222B}', 0xF2),
+    ('\u{2234}', 0x5C),
+    ('\u{223C}', 0x7E),
+    ('\u{2245}', 0x40),
+    ('\u{2248}', 0xBB),
+    ('\u{2260}', 0xB9),
+    ('\u{2261}', 0xBA),
+    ('\u{2264}', 0xA3),
+    ('\u{2265}', 0xB3),
+    ('\u{2282}', 0xCC),
+    ('\u{2283}', 0xC9),
+    ('\u{2284}', 0xCB),
+    ('\u{2286}', 0xCD),
+    ('\u{2287}', 0xCA),
+    ('\u{2295}', 0xC5),
+    ('\u{2297}', 0xC4),
+    ('\u{22A5}', 0x5E),
+    ('\u{22C5}', 0xD7),
+    ('\u{2320}', 0xF3),
+    ('\u{2321}', 0xF5),
+    ('\u{2329}', 0xE1),
+    ('\u{232A}', 0xF1),
+    ('\u{25CA}', 0xE0),
+    ('\u{2660}', 0xAA),
+    ('\u{2661}', 0xA9),
+    ('\u{2662}', 0xA8),
+    ('\u{2663}', 0xA7),
+    ('\u{F6D9}', 0xD3),
+    ('\u{F6DA}', 0xD2),
+    ('\u{F6DB}', 0xD4),
+    ('\u{F8E5}', 0x60),
+    ('\u{F8E7}', 0xBE),
+    ('\u{F8E8}', 0xE2),
+    ('\u{F8E9}', 0xE3),
+    ('\u{F8EA}', 0xE4),
+    ('\u{F8EB}', 0xE6),
+    ('\u{F8ED}', 0xE8),
+    ('\u{F8EE}', 0xE9),
+    ('\u{F8EF}', 0xEA),
+    ('\u{F8F0}', 0xEB),
+    ('\u{F8F1}', 0xEC),
+    ('\u{F8F3}', 0xEE),
+    ('\u{F8F5}', 0xF4),
+    ('\u{F8F6}', 0xF6),
+    ('\u{F8F8}', 0xF8),
+    ('\u{F8F9}', 0xF9),
+    ('\u{F8FA}', 0xFA),
+    ('\u{F8FB}', 0xFB),
+    ('\u{F8FC}', 0xFC),
+    ('\u{F8FE}', 0xFE),
+];
+
+/// The `ZapfDingbats` glyphs Unicode has outside of the Dingbats block, with their codes.
+const ZAPF_DINGBATS_ELSEWHERE: [(char, u8); 28] = [
+    (' ', 0x20),
+    ('\u{260E}', 0x25),
+    ('\u{261B}', 0x2A),
+    ('\u{261E}', 0x2B),
+    ('\u{2605}', 0x48),
+    ('\u{25CF}', 0x6C),
+    ('\u{25A0}', 0x6E),
+    ('\u{25B2}', 0x73),
+    ('\u{25BC}', 0x74),
+    ('\u{25C6}', 0x75),
+    ('\u{25D7}', 0x77),
+    ('\u{2663}', 0xA8),
+    ('\u{2666}', 0xA9),
+    ('\u{2665}', 0xAA),
+    ('\u{2660}', 0xAB),
+    ('\u{2460}', 0xAC),
+    ('\u{2461}', 0xAD),
+    ('\u{2462}', 0xAE),
+    ('\u{2463}', 0xAF),
+    ('\u{2464}', 0xB0),
+    ('\u{2465}', 0xB1),
+    ('\u{2466}', 0xB2),
+    ('\u{2467}', 0xB3),
+    ('\u{2468}', 0xB4),
+    ('\u{2469}', 0xB5),
+    ('\u{2192}', 0xD5),
+    ('\u{2194}', 0xD6),
+    ('\u{2195}', 0xD7),
+];
+
+/// The characters of the Dingbats block that were left out for the glyphs Unicode has
+/// elsewhere, & later given to other symbols.
+const DINGBATS_GAPS: [char; 10] = [
+    '\u{2705}', '\u{270A}', '\u{270B}', '\u{2728}', '\u{274C}', '\u{274E}', '\u{2753}', '\u{2754}',
+    '\u{2755}', '\u{2757}',
+];
+
+/// The code of a character in `ZapfDingbats`, which follows the order of the Dingbats block.
+fn zapf_dingbats_code(c: char) -> Option<u8> {
+    if let Some((_, code)) = ZAPF_DINGBATS_ELSEWHERE
+        .iter()
+        .find(|(character, _)| *character == c)
+    {
+        return Some(*code);
+    }
+    if DINGBATS_GAPS.contains(&c) {
+        return None;
+    }
+    let (first, first_code) = match c {
+        '\u{2701}'..='\u{275E}' => ('\u{2701}', 0x21),
+        '\u{2761}'..='\u{2767}' => ('\u{2761}', 0xA1),
+        '\u{2768}'..='\u{2775}' => ('\u{2768}', 0x80),
+        '\u{2776}'..='\u{2794}' => ('\u{2776}', 0xB6),
+        '\u{2798}'..='\u{27AF}' => ('\u{2798}', 0xD8),
+        '\u{27B1}'..='\u{27BE}' => ('\u{27B1}', 0xF1),
+        _ => return None,
+    };
+    u8::try_from(u32::from(c) - u32::from(first) + first_code).ok()
+}
+
+/// The metrics of `Times-Roman`.
+pub(super) const TIMES_ROMAN: BuiltinMetrics = BuiltinMetrics {
+    ascent: 683,
+    descent: -217,
+    widths: [
+        250, 333, 408, 500, 500, 833, 778, 180, 333, 333, 500, 564, 250, 333, 250, 278, 500, 500,
+        500, 500, 500, 500, 500, 500, 500, 500, 278, 278, 564, 564, 564, 444, 921, 722, 667, 667,
+        722, 611, 556, 722, 722, 333, 389, 722, 611, 889, 722, 722, 556, 722, 667, 556, 611, 722,
+        722, 944, 722, 722, 611, 333, 278, 333, 469, 500, 333, 444, 500, 444, 500, 444, 333, 500,
+        500, 278, 278, 500, 278, 778, 500, 500, 500, 500, 333, 389, 278, 500, 500, 722, 500, 500,
+        444, 480, 200, 480, 541, 350, 500, 350, 333, 500, 444, 1000, 500, 500, 333, 1000, 556, 333,
+        889, 350, 611, 350, 350, 333, 333, 444, 444, 350, 500, 1000, 333, 980, 389, 333, 722, 350,
+        444, 722, 250, 333, 500, 500, 500, 500, 200, 500, 333, 760, 276, 500, 564, 333, 760, 333,
+        400, 564, 300, 300, 333, 500, 453, 250, 333, 300, 310, 500, 750, 750, 750, 444, 722, 722,
+        722, 722, 722, 722, 889, 667, 611, 611, 611, 611, 333, 333, 333, 333, 722, 722, 722, 722,
+        722, 722, 722, 564, 722, 722, 722, 722, 722, 722, 556, 500, 444, 444, 444, 444, 444, 444,
+        667, 444, 444, 444, 444, 444, 278, 278, 278, 278, 500, 500, 500, 500, 500, 500, 500, 564,
+        500, 500, 500, 500, 500, 500, 500, 500,
+    ],
+    encoding: BuiltinEncoding::WinAnsi,
+};
+
+/// The metrics of `Times-Bold`.
+pub(super) const TIMES_BOLD: BuiltinMetrics = BuiltinMetrics {
+    ascent: 683,
+    descent: -217,
+    widths: [
+        250, 333, 555, 500, 500, 1000, 833, 278, 333, 333, 500, 570, 250, 333, 250, 278, 500, 500,
+        500, 500, 500, 500, 500, 500, 500, 500, 333, 333, 570, 570, 570, 500, 930, 722, 667, 722,
+        722, 667, 611, 778, 778, 389, 500, 778, 667, 944, 722, 778, 611, 778, 722, 556, 667, 722,
+        722, 1000, 722, 722, 667, 333, 278, 333, 581, 500, 333, 500, 556, 444, 556, 444, 333, 500,
+        556, 278, 333, 556, 278, 833, 556, 500, 556, 556, 444, 389, 333, 556, 500, 722, 500, 500,
+        444, 394, 220, 394, 520, 350, 500, 350, 333, 500, 500, 1000, 500, 500, 333, 1000, 556, 333,
+        1000, 350, 667, 350, 350, 333, 333, 500, 500, 350, 500, 1000, 333, 1000, 389, 333, 722,
+        350, 444, 722, 250, 333, 500, 500, 500, 500, 220, 500, 333, 747, 300, 500, 570, 333, 747,
+        333, 400, 570, 300, 300, 333, 556, 540, 250, 333, 300, 330, 500, 750, 750, 750, 500, 722,
+        722, 722, 722, 722, 722, 1000, 722, 667, 667, 667, 667, 389, 389, 389, 389, 722, 722, 778,
+        778, 778, 778, 778, 570, 778, 722, 722, 722, 722, 722, 611, 556, 500, 500, 500, 500, 500,
+        500, 722, 444, 444, 444, 444, 444, 278, 278, 278, 278, 500, 556, 500, 500, 500, 500, 500,
+        570, 500, 556, 556, 556, 556, 500, 556, 500,
+    ],
+    encoding: BuiltinEncoding::WinAnsi,
+};
+
+/// The metrics of `Times-Italic`.
+pub(super) const TIMES_ITALIC: BuiltinMetrics = BuiltinMetrics {
+    ascent: 683,
+    descent: -217,
+    widths: [
+        250, 333, 420, 500, 500, 833, 778, 214, 333, 333, 500, 675, 250, 333, 250, 278, 500, 500,
+        500, 500, 500, 500, 500, 500, 500, 500, 333, 333, 675, 675, 675, 500, 920, 611, 611, 667,
+        722, 611, 611, 722, 722, 333, 444, 667, 556, 833, 667, 722, 611, 722, 611, 500, 556, 722,
+        611, 833, 611, 556, 556, 389, 278, 389, 422, 500, 333, 500, 500, 444, 500, 444, 278, 500,
+        500, 278, 278, 444, 278, 722, 500, 500, 500, 500, 389, 389, 278, 500, 444, 667, 444, 444,
+        389, 400, 275, 400, 541, 350, 500, 350, 333, 500, 556, 889, 500, 500, 333, 1000, 500, 333,
+        944, 350, 556, 350, 350, 333, 333, 556, 556, 350, 500, 889, 333, 980, 389, 333, 667, 350,
+        389, 556, 250, 389, 500, 500, 500, 500, 275, 500, 333, 760, 276, 500, 675, 333, 760, 333,
+        400, 675, 300, 300, 333, 500, 523, 250, 333, 300, 310, 500, 750, 750, 750, 500, 611, 611,
+        611, 611, 611, 611, 889, 667, 611, 611, 611, 611, 333, 333, 333, 333, 722, 667, 722, 722,
+        722, 722, 722, 675, 722, 722, 722, 722, 722, 556, 611, 500, 500, 500, 500, 500, 500, 500,
+        667, 444, 444, 444, 444, 444, 278, 278, 278, 278, 500, 500, 500, 500, 500, 500, 500, 675,
+        500, 500, 500, 500, 500, 444, 500, 444,
+    ],
+    encoding: BuiltinEncoding::WinAnsi,
+};
+
+/// The metrics of `Times-BoldItalic`.
+pub(super) const TIMES_BOLD_ITALIC: BuiltinMetrics = BuiltinMetrics {
+    ascent: 683,
+    descent: -217,
+    widths: [
+        250, 389, 555, 500, 500, 833, 778, 278, 333, 333, 500, 570, 250, 333, 250, 278, 500, 500,
+        500, 500, 500, 500, 500, 500, 500, 500, 333, 333, 570, 570, 570, 500, 832, 667, 667, 667,
+        722, 667, 667, 722, 778, 389, 500, 667, 611, 889, 722, 722, 611, 722, 667, 556, 611, 722,
+        667, 889, 667, 611, 611, 333, 278, 333, 570, 500, 333, 500, 500, 444, 500, 444, 333, 500,
+        556, 278, 278, 500, 278, 778, 556, 500, 500, 500, 389, 389, 278, 556, 444, 667, 500, 444,
+        389, 348, 220, 348, 570, 350, 500, 350, 333, 500, 500, 1000, 500, 500, 333, 1000, 556, 333,
+        944, 350, 611, 350, 350, 333, 333, 500, 500, 350, 500, 1000, 333, 1000, 389, 333, 722, 350,
+        389, 611, 250, 389, 500, 500, 500, 500, 220, 500, 333, 747, 266, 500, 606, 333, 747, 333,
+        400, 570, 300, 300, 333, 576, 500, 250, 333, 300, 300, 500, 750, 750, 750, 500, 667, 667,
+        667, 667, 667, 667, 944, 667, 667, 667, 667, 667, 389, 389, 389, 389, 722, 722, 722, 722,
+        722, 722, 722, 570, 722, 722, 722, 722, 722, 611, 611, 500, 500, 500, 500, 500, 500, 500,
+        722, 444, 444, 444, 444, 444, 278, 278, 278, 278, 500, 556, 500, 500, 500, 500, 500, 570,
+        500, 556, 556, 556, 556, 444, 500, 444,
+    ],
+    encoding: BuiltinEncoding::WinAnsi,
+};
+
+/// The metrics of `Helvetica`.
+pub(super) const HELVETICA: BuiltinMetrics = BuiltinMetrics {
+    ascent: 718,
+    descent: -207,
+    widths: [
+        278, 278, 355, 556, 556, 889, 667, 191, 333, 333, 389, 584, 278, 333, 278, 278, 556, 556,
+        556, 556, 556, 556, 556, 556, 556, 556, 278, 278, 584, 584, 584, 556, 1015, 667, 667, 722,
+        722, 667, 611, 778, 722, 278, 500, 667, 556, 833, 722, 778, 667, 778, 722, 667, 611, 722,
+        667, 944, 667, 667, 611, 278, 278, 278, 469, 556, 333, 556, 556, 500, 556, 556, 278, 556,
+        556, 222, 222, 500, 222, 833, 556, 556, 556, 556, 333, 500, 278, 556, 500, 722, 500, 500,
+        500, 334, 260, 334, 584, 350, 556, 350, 222, 556, 333, 1000, 556, 556, 333, 1000, 667, 333,
+        1000, 350, 611, 350, 350, 222, 222, 333, 333, 350, 556, 1000, 333, 1000, 500, 333, 944,
+        350, 500, 667, 278, 333, 556, 556, 556, 556, 260, 556, 333, 737, 370, 556, 584, 333, 737,
+        333, 400, 584, 333, 333, 333, 556, 537, 278, 333, 333, 365, 556, 834, 834, 834, 611, 667,
+        667, 667, 667, 667, 667, 1000, 722, 667, 667, 667, 667, 278, 278, 278, 278, 722, 722, 778,
+        778, 778, 778, 778, 584, 778, 722, 722, 722, 722, 667, 667, 611, 556, 556, 556, 556, 556,
+        556, 889, 500, 556, 556, 556, 556, 278, 278, 278, 278, 556, 556, 556, 556, 556, 556, 556,
+        584, 611, 556, 556, 556, 556, 500, 556, 500,
+    ],
+    encoding: BuiltinEncoding::WinAnsi,
+};
+
+/// The metrics of `Helvetica-Bold`.
+pub(super) const HELVETICA_BOLD: BuiltinMetrics = BuiltinMetrics {
+    ascent: 718,
+    descent: -207,
+    widths: [
+        278, 333, 474, 556, 556, 889, 722, 238, 333, 333, 389, 584, 278, 333, 278, 278, 556, 556,
+        556, 556, 556, 556, 556, 556, 556, 556, 333, 333, 584, 584, 584, 611, 975, 722, 722, 722,
+        722, 667, 611, 778, 722, 278, 556, 722, 611, 833, 722, 778, 667, 778, 722, 667, 611, 722,
+        667, 944, 667, 667, 611, 333, 278, 333, 584, 556, 333, 556, 611, 556, 611, 556, 333, 611,
+        611, 278, 278, 556, 278, 889, 611, 611, 611, 611, 389, 556, 333, 611, 556, 778, 556, 556,
+        500, 389, 280, 389, 584, 350, 556, 350, 278, 556, 500, 1000, 556, 556, 333, 1000, 667, 333,
+        1000, 350, 611, 350, 350, 278, 278, 500, 500, 350, 556, 1000, 333, 1000, 556, 333, 944,
+        350, 500, 667, 278, 333, 556, 556, 556, 556, 280, 556, 333, 737, 370, 556, 584, 333, 737,
+        333, 400, 584, 333, 333, 333, 611, 556, 278, 333, 333, 365, 556, 834, 834, 834, 611, 722,
+        722, 722, 722, 722, 722, 1000, 722, 667, 667, 667, 667, 278, 278, 278, 278, 722, 722, 778,
+        778, 778, 778, 778, 584, 778, 722, 722, 722, 722, 667, 667, 611, 556, 556, 556, 556, 556,
+        556, 889, 556, 556, 556, 556, 556, 278, 278, 278, 278, 611, 611, 611, 611, 611, 611, 611,
+        584, 611, 611, 611, 611, 611, 556, 611, 556,
+    ],
+    encoding: BuiltinEncoding::WinAnsi,
+};
+
+/// The metrics of `Helvetica-Oblique`.
+pub(super) const HELVETICA_OBLIQUE: BuiltinMetrics = BuiltinMetrics {
+    ascent: 718,
+    descent: -207,
+    widths: [
+        278, 278, 355, 556, 556, 889, 667, 191, 333, 333, 389, 584, 278, 333, 278, 278, 556, 556,
+        556, 556, 556, 556, 556, 556, 556, 556, 278, 278, 584, 584, 584, 556, 1015, 667, 667, 722,
+        722, 667, 611, 778, 722, 278, 500, 667, 556, 833, 722, 778, 667, 778, 722, 667, 611, 722,
+        667, 944, 667, 667, 611, 278, 278, 278, 469, 556, 333, 556, 556, 500, 556, 556, 278, 556,
+        556, 222, 222, 500, 222, 833, 556, 556, 556, 556, 333, 500, 278, 556, 500, 722, 500, 500,
+        500, 334, 260, 334, 584, 350, 556, 350, 222, 556, 333, 1000, 556, 556, 333, 1000, 667, 333,
+        1000, 350, 611, 350, 350, 222, 222, 333, 333, 350, 556, 1000, 333, 1000, 500, 333, 944,
+        350, 500, 667, 278, 333, 556, 556, 556, 556, 260, 556, 333, 737, 370, 556, 584, 333, 737,
+        333, 400, 584, 333, 333, 333, 556, 537, 278, 333, 333, 365, 556, 834, 834, 834, 611, 667,
+        667, 667, 667, 667, 667, 1000, 722, 667, 667, 667, 667, 278, 278, 278, 278, 722, 722, 778,
+        778, 778, 778, 778, 584, 778, 722, 722, 722, 722, 667, 667, 611, 556, 556, 556, 556, 556,
+        556, 889, 500, 556, 556, 556, 556, 278, 278, 278, 278, 556, 556, 556, 556, 556, 556, 556,
+        584, 611, 556, 556, 556, 556, 500, 556, 500,
+    ],
+    encoding: BuiltinEncoding::WinAnsi,
+};
+
+/// The metrics of `Helvetica-BoldOblique`.
+pub(super) const HELVETICA_BOLD_OBLIQUE: BuiltinMetrics = BuiltinMetrics {
+    ascent: 718,
+    descent: -207,
+    widths: [
+        278, 333, 474, 556, 556, 889, 722, 238, 333, 333, 389, 584, 278, 333, 278, 278, 556, 556,
+        556, 556, 556, 556, 556, 556, 556, 556, 333, 333, 584, 584, 584, 611, 975, 722, 722, 722,
+        722, 667, 611, 778, 722, 278, 556, 722, 611, 833, 722, 778, 667, 778, 722, 667, 611, 722,
+        667, 944, 667, 667, 611, 333, 278, 333, 584, 556, 333, 556, 611, 556, 611, 556, 333, 611,
+        611, 278, 278, 556, 278, 889, 611, 611, 611, 611, 389, 556, 333, 611, 556, 778, 556, 556,
+        500, 389, 280, 389, 584, 350, 556, 350, 278, 556, 500, 1000, 556, 556, 333, 1000, 667, 333,
+        1000, 350, 611, 350, 350, 278, 278, 500, 500, 350, 556, 1000, 333, 1000, 556, 333, 944,
+        350, 500, 667, 278, 333, 556, 556, 556, 556, 280, 556, 333, 737, 370, 556, 584, 333, 737,
+        333, 400, 584, 333, 333, 333, 611, 556, 278, 333, 333, 365, 556, 834, 834, 834, 611, 722,
+        722, 722, 722, 722, 722, 1000, 722, 667, 667, 667, 667, 278, 278, 278, 278, 722, 722, 778,
+        778, 778, 778, 778, 584, 778, 722, 722, 722, 722, 667, 667, 611, 556, 556, 556, 556, 556,
+        556, 889, 556, 556, 556, 556, 556, 278, 278, 278, 278, 611, 611, 611, 611, 611, 611, 611,
+        584, 611, 611, 611, 611, 611, 556, 611, 556,
+    ],
+    encoding: BuiltinEncoding::WinAnsi,
+};
+
+/// The metrics of `Courier`.
+pub(super) const COURIER: BuiltinMetrics = BuiltinMetrics {
+    ascent: 629,
+    descent: -157,
+    widths: [
+        600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600,
+        600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600,
+        600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600,
+        600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600,
+        600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600,
+        600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600,
+        600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600,
+        600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600,
+        600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600,
+        600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600,
+        600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600,
+        600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600,
+        600, 600, 600, 600, 600, 600, 600, 600,
+    ],
+    encoding: BuiltinEncoding::WinAnsi,
+};
+
+/// The metrics of `Courier-Bold`.
+pub(super) const COURIER_BOLD: BuiltinMetrics = BuiltinMetrics {
+    ascent: 629,
+    descent: -157,
+    widths: [
+        600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600,
+        600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600,
+        600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600,
+        600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600,
+        600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600,
+        600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600,
+        600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600,
+        600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600,
+        600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600,
+        600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600,
+        600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600,
+        600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600,
+        600, 600, 600, 600, 600, 600, 600, 600,
+    ],
+    encoding: BuiltinEncoding::WinAnsi,
+};
+
+/// The metrics of `Courier-Oblique`.
+pub(super) const COURIER_OBLIQUE: BuiltinMetrics = BuiltinMetrics {
+    ascent: 629,
+    descent: -157,
+    widths: [
+        600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600,
+        600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600,
+        600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600,
+        600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600,
+        600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600,
+        600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600,
+        600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600,
+        600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600,
+        600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600,
+        600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600,
+        600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600,
+        600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600,
+        600, 600, 600, 600, 600, 600, 600, 600,
+    ],
+    encoding: BuiltinEncoding::WinAnsi,
+};
+
+/// The metrics of `Courier-BoldOblique`.
+pub(super) const COURIER_BOLD_OBLIQUE: BuiltinMetrics = BuiltinMetrics {
+    ascent: 629,
+    descent: -157,
+    widths: [
+        600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600,
+        600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600,
+        600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600,
+        600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600,
+        600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600,
+        600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600,
+        600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600,
+        600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600,
+        600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600,
+        600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600,
+        600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600,
+        600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600, 600,
+        600, 600, 600, 600, 600, 600, 600, 600,
+    ],
+    encoding: BuiltinEncoding::WinAnsi,
+};
+
+/// The metrics of `Symbol`.
+pub(super) const SYMBOL: BuiltinMetrics = BuiltinMetrics {
+    ascent: 1010,
+    descent: -293,
+    widths: [
+        250, 333, 713, 500, 549, 833, 778, 439, 333, 333, 500, 549, 250, 549, 250, 278, 500, 500,
+        500, 500, 500, 500, 500, 500, 500, 500, 278, 278, 549, 549, 549, 444, 549, 722, 667, 722,
+        612, 611, 763, 603, 722, 333, 631, 722, 686, 889, 722, 722, 768, 741, 556, 592, 611, 690,
+        439, 768, 645, 795, 611, 333, 863, 333, 658, 500, 500, 631, 549, 549, 494, 439, 521, 411,
+        603, 329, 603, 549, 549, 576, 521, 549, 549, 521, 549, 603, 439, 576, 713, 686, 493, 686,
+        494, 480, 200, 480, 549, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 750, 620, 247, 549, 167, 713, 500, 753, 753, 753, 753,
+        1042, 987, 603, 987, 603, 400, 549, 411, 549, 549, 713, 494, 460, 549, 549, 549, 549, 1000,
+        603, 1000, 658, 823, 686, 795, 987, 768, 768, 823, 768, 768, 713, 713, 713, 713, 713, 713,
+        713, 768, 713, 790, 790, 890, 823, 549, 250, 713, 603, 603, 1042, 987, 603, 987, 603, 494,
+        329, 790, 790, 786, 713, 384, 384, 384, 384, 384, 384, 494, 494, 494, 494, 0, 329, 274,
+        686, 686, 686, 384, 384, 384, 384, 384, 384, 494, 494, 494, 0,
+    ],
+    encoding: BuiltinEncoding::Symbol,
+};
+
+/// The metrics of `ZapfDingbats`.
+pub(super) const ZAPF_DINGBATS: BuiltinMetrics = BuiltinMetrics {
+    ascent: 820,
+    descent: -143,
+    widths: [
+        278, 974, 961, 974, 980, 719, 789, 790, 791, 690, 960, 939, 549, 855, 911, 933, 911, 945,
+        974, 755, 846, 762, 761, 571, 677, 763, 760, 759, 754, 494, 552, 537, 577, 692, 786, 788,
+        788, 790, 793, 794, 816, 823, 789, 841, 823, 833, 816, 831, 923, 744, 723, 749, 790, 792,
+        695, 776, 768, 792, 759, 707, 708, 682, 701, 826, 815, 789, 789, 707, 687, 696, 689, 786,
+        787, 713, 791, 785, 791, 873, 761, 762, 762, 759, 759, 892, 892, 788, 784, 438, 138, 277,
+        415, 392, 392, 668, 668, 0, 390, 390, 317, 317, 276, 276, 509, 509, 410, 410, 234, 234,
+        334, 334, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 732, 544, 544, 910, 667,
+        760, 760, 776, 595, 694, 626, 788, 788, 788, 788, 788, 788, 788, 788, 788, 788, 788, 788,
+        788, 788, 788, 788, 788, 788, 788, 788, 788, 788, 788, 788, 788, 788, 788, 788, 788, 788,
+        788, 788, 788, 788, 788, 788, 788, 788, 788, 788, 894, 838, 1016, 458, 748, 924, 748, 918,
+        927, 928, 928, 834, 873, 828, 924, 924, 917, 930, 931, 463, 883, 836, 836, 867, 867, 696,
+        696, 874, 0, 874, 760, 946, 771, 865, 771, 888, 967, 888, 831, 873, 927, 970, 918, 0,
+    ],
+    encoding: BuiltinEncoding::ZapfDingbats,
+};
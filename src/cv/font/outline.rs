@@ -0,0 +1,456 @@
+//! Converts text drawn with embedded TrueType fonts into vector outlines, so the PDF needs no
+//! embedded fonts. The text state of the page contents is replayed to place each glyph's
+//! contours where the glyph would have been drawn.
+//! The text itself can be kept as an invisible layer, so it can still be searched & copied.
+
+use super::subset::{font_file, page_fonts, rename_font, untagged};
+use anyhow::Result;
+use printpdf::lopdf::content::{Content, Operation};
+use printpdf::lopdf::{Dictionary, Document, Object, ObjectId};
+use std::collections::{BTreeSet, HashMap};
+use ttf_parser::name_id::POST_SCRIPT_NAME;
+use ttf_parser::{Face, GlyphId, OutlineBuilder};
+
+/// A transformation matrix, as `[a b c d e f]`.
+type Matrix = [f64; 6];
+
+const IDENTITY: Matrix = [1.0_f64, 0.0_f64, 0.0_f64, 1.0_f64, 0.0_f64, 0.0_f64];
+/// The text rendering mode that neither fills nor strokes the text.
+const INVISIBLE: i64 = 3;
+/// The decimal places kept in the coordinates of outlines.
+const PRECISION: f64 = 1000.0_f64;
+
+/// Applies `first`, then `second`.
+fn multiply(first: Matrix, second: Matrix) -> Matrix {
+    let [xx, xy, yx, yy, dx, dy] = first;
+    let [xx2, xy2, yx2, yy2, dx2, dy2] = second;
+    [
+        xx.mul_add(xx2, xy * yx2),
+        xx.mul_add(xy2, xy * yy2),
+        yx.mul_add(xx2, yy * yx2),
+        yx.mul_add(xy2, yy * yy2),
+        dx.mul_add(xx2, dy.mul_add(yx2, dx2)),
+        dx.mul_add(xy2, dy.mul_add(yy2, dy2)),
+    ]
+}
+
+fn translate(x: f64, y: f64) -> Matrix {
+    [1.0_f64, 0.0_f64, 0.0_f64, 1.0_f64, x, y]
+}
+
+/// The parameters of the text state that are saved & restored with the graphics state.
+#[derive(Clone, Copy)]
+struct TextState {
+    font: Option<ObjectId>,
+    font_size: f64,
+    character_spacing: f64,
+    /// The horizontal scaling as a fraction.
+    scaling: f64,
+    leading: f64,
+    rise: f64,
+    rendering_mode: i64,
+}
+
+impl Default for TextState {
+    fn default() -> Self {
+        TextState {
+            font: None,
+            font_size: 0.0_f64,
+            character_spacing: 0.0_f64,
+            scaling: 1.0_f64,
+            leading: 0.0_f64,
+            rise: 0.0_f64,
+            rendering_mode: 0,
+        }
+    }
+}
+
+/// Writes the contours of a glyph as path operations in user space.
+struct PathWriter<'o> {
+    matrix: Matrix,
+    operations: &'o mut Vec<Operation>,
+    /// The current point in font units, for turning quadratic curves into cubic ones.
+    current: (f64, f64),
+}
+
+impl PathWriter<'_> {
+    fn push(&mut self, operator: &str, points: &[(f64, f64)]) {
+        let [xx, xy, yx, yy, dx, dy] = self.matrix;
+        let operands = points
+            .iter()
+            .flat_map(|(x, y)| {
+                [
+                    xx.mul_add(*x, yx.mul_add(*y, dx)),
+                    xy.mul_add(*x, yy.mul_add(*y, dy)),
+                ]
+            })
+            .map(|value| Object::Real((value * PRECISION).round() / PRECISION))
+            .collect();
+        self.operations.push(Operation::new(operator, operands));
+    }
+}
+
+impl OutlineBuilder for PathWriter<'_> {
+    fn move_to(&mut self, x: f32, y: f32) {
+        self.current = (f64::from(x), f64::from(y));
+        self.push("m", &[self.current]);
+    }
+
+    fn line_to(&mut self, x: f32, y: f32) {
+        self.current = (f64::from(x), f64::from(y));
+        self.push("l", &[self.current]);
+    }
+
+    fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+        let (x0, y0) = self.current;
+        let (qx, qy) = (f64::from(x1), f64::from(y1));
+        let (x3, y3) = (f64::from(x), f64::from(y));
+        let two_thirds = 2.0_f64 / 3.0_f64;
+        let first = (
+            two_thirds.mul_add(qx - x0, x0),
+            two_thirds.mul_add(qy - y0, y0),
+        );
+        let second = (
+            two_thirds.mul_add(qx - x3, x3),
+            two_thirds.mul_add(qy - y3, y3),
+        );
+        self.current = (x3, y3);
+        self.push("c", &[first, second, self.current]);
+    }
+
+    fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+        self.current = (f64::from(x), f64::from(y));
+        self.push(
+            "c",
+            &[
+                (f64::from(x1), f64::from(y1)),
+                (f64::from(x2), f64::from(y2)),
+                self.current,
+            ],
+        );
+    }
+
+    fn close(&mut self) {
+        self.push("h", &[]);
+    }
+}
+
+/// The operator painting glyph outlines the way a text rendering mode paints glyphs.
+/// Clipping is dropped, as the text commands never clip.
+fn paint_operator(rendering_mode: i64) -> Option<&'static str> {
+    match rendering_mode.rem_euclid(4) {
+        0 => Some("f"),
+        1 => Some("S"),
+        2 => Some("B"),
+        _ => None,
+    }
+}
+
+/// Replays the text operations of a page, turning the glyphs of outlined fonts into paths.
+struct Outliner<'f> {
+    faces: &'f HashMap<ObjectId, Face<'f>>,
+    fonts: HashMap<Vec<u8>, ObjectId>,
+    keep_text: bool,
+    state: TextState,
+    saved: Vec<TextState>,
+    text_matrix: Matrix,
+    line_matrix: Matrix,
+    /// The outlines of the current text object, drawn once it ends.
+    outlines: Vec<Operation>,
+    operations: Vec<Operation>,
+}
+
+impl<'f> Outliner<'f> {
+    fn face(&self) -> Option<&'f Face<'f>> {
+        self.state.font.and_then(|font| self.faces.get(&font))
+    }
+
+    fn move_line(&mut self, x: f64, y: f64) {
+        self.line_matrix = multiply(translate(x, y), self.line_matrix);
+        self.text_matrix = self.line_matrix;
+    }
+
+    /// Moves the text matrix along by a horizontal displacement in thousandths of the font size.
+    fn advance(&mut self, thousandths: f64, spacing: f64) {
+        let state = self.state;
+        let x = (thousandths / 1000.0_f64).mul_add(state.font_size, spacing) * state.scaling;
+        self.text_matrix = multiply(translate(x, 0.0_f64), self.text_matrix);
+    }
+
+    /// Adds the outlines of a string of two byte glyph IDs & moves past them.
+    fn outline_string(&mut self, string: &[u8]) {
+        let state = self.state;
+        let Some(face) = self.face() else {
+            return;
+        };
+        let units_per_em = f64::from(face.units_per_em());
+        let paint = paint_operator(state.rendering_mode);
+        let glyphs: Vec<(GlyphId, f64)> = string
+            .chunks_exact(2)
+            .filter_map(|code| {
+                let glyph = GlyphId(u16::from_be_bytes(code.try_into().ok()?));
+                let advance = f64::from(face.glyph_hor_advance(glyph).unwrap_or_default());
+                Some((glyph, advance * 1000.0_f64 / units_per_em))
+            })
+            .collect();
+        for (glyph, advance) in glyphs {
+            if let Some(operator) = paint {
+                let font_matrix = [
+                    state.font_size * state.scaling / units_per_em,
+                    0.0_f64,
+                    0.0_f64,
+                    state.font_size / units_per_em,
+                    0.0_f64,
+                    state.rise,
+                ];
+                let start = self.outlines.len();
+                let mut writer = PathWriter {
+                    matrix: multiply(font_matrix, self.text_matrix),
+                    operations: &mut self.outlines,
+                    current: (0.0_f64, 0.0_f64),
+                };
+                if face.outline_glyph(glyph, &mut writer).is_some() {
+                    self.outlines.push(Operation::new(operator, Vec::new()));
+                } else {
+                    // Blank glyphs such as spaces
+                    self.outlines.truncate(start);
+                }
+            }
+            self.advance(advance, state.character_spacing);
+        }
+    }
+
+    /// Handles an operation of the page contents, returning whether to keep it as it is.
+    fn process(&mut self, operation: &Operation) -> bool {
+        let numbers: Vec<f64> = operation
+            .operands
+            .iter()
+            .filter_map(|operand| operand.as_float().ok())
+            .collect();
+        let number = |index: usize| numbers.get(index).copied().unwrap_or_default();
+        match operation.operator.as_str() {
+            "q" => self.saved.push(self.state),
+            "Q" => self.state = self.saved.pop().unwrap_or_default(),
+            "BT" => {
+                self.text_matrix = IDENTITY;
+                self.line_matrix = IDENTITY;
+            }
+            "ET" => {
+                self.operations.push(operation.clone());
+                self.operations.append(&mut self.outlines);
+                return false;
+            }
+            "Tf" => {
+                self.state.font = operation
+                    .operands
+                    .first()
+                    .and_then(|name| name.as_name().ok())
+                    .and_then(|name| self.fonts.get(name))
+                    .copied();
+                self.state.font_size = number(0);
+                return self.keep_text || self.face().is_none();
+            }
+            "Tc" => self.state.character_spacing = number(0),
+            "Tz" => self.state.scaling = number(0) / 100.0_f64,
+            "TL" => self.state.leading = number(0),
+            "Ts" => self.state.rise = number(0),
+            "Tr" => {
+                self.state.rendering_mode = operation
+                    .operands
+                    .first()
+                    .and_then(|mode| mode.as_i64().ok())
+                    .unwrap_or_default();
+            }
+            "Td" => self.move_line(number(0), number(1)),
+            "TD" => {
+                self.state.leading = -number(1);
+                self.move_line(number(0), number(1));
+            }
+            "Tm" => {
+                self.line_matrix = [
+                    number(0),
+                    number(1),
+                    number(2),
+                    number(3),
+                    number(4),
+                    number(5),
+                ];
+                self.text_matrix = self.line_matrix;
+            }
+            "T*" => self.move_line(0.0_f64, -self.state.leading),
+            "Tj" | "'" | "\"" | "TJ" => return self.process_text(operation),
+            _ => {}
+        }
+        true
+    }
+
+    /// Outlines the glyphs shown by a text operator, returning whether to keep it as it is.
+    /// Kept text is made invisible.
+    fn process_text(&mut self, operation: &Operation) -> bool {
+        if self.face().is_none() {
+            return true;
+        }
+        match operation.operator.as_str() {
+            "'" => self.move_line(0.0_f64, -self.state.leading),
+            "\"" => {
+                self.state.character_spacing = operation
+                    .operands
+                    .get(1)
+                    .and_then(|spacing| spacing.as_float().ok())
+                    .unwrap_or_default();
+                self.move_line(0.0_f64, -self.state.leading);
+            }
+            _ => {}
+        }
+        let items = match operation.operator.as_str() {
+            "TJ" => operation
+                .operands
+                .first()
+                .and_then(|items| items.as_array().ok())
+                .cloned()
+                .unwrap_or_default(),
+            _ => operation.operands.last().cloned().into_iter().collect(),
+        };
+        for item in items {
+            if let Ok(string) = item.as_str() {
+                self.outline_string(string);
+            } else if let Ok(adjustment) = item.as_float() {
+                self.advance(-adjustment, 0.0_f64);
+            }
+        }
+        if self.keep_text {
+            // The rendering mode lasts beyond the text object, so it is set back afterwards
+            let mode = |mode| Operation::new("Tr", vec![Object::Integer(mode)]);
+            self.operations.push(mode(INVISIBLE));
+            self.operations.push(operation.clone());
+            self.operations.push(mode(self.state.rendering_mode));
+        }
+        false
+    }
+}
+
+/// Removes the fonts from the font resources, once nothing draws with them.
+pub(crate) fn remove_font_resources(document: &mut Document, removed: &BTreeSet<ObjectId>) {
+    let referenced: Vec<ObjectId> = document
+        .objects
+        .values()
+        .filter_map(|object| match object {
+            Object::Dictionary(dictionary) => dictionary.get(b"Font").ok(),
+            Object::Stream(stream) => stream.dict.get(b"Font").ok(),
+            _ => None,
+        })
+        .filter_map(|font| font.as_reference().ok())
+        .collect();
+    for object in document.objects.values_mut() {
+        let dictionary = match object {
+            Object::Dictionary(dictionary) => dictionary,
+            Object::Stream(stream) => &mut stream.dict,
+            _ => continue,
+        };
+        let Ok(Object::Dictionary(fonts)) = dictionary.get_mut(b"Font") else {
+            continue;
+        };
+        remove_fonts(fonts, removed);
+    }
+    for id in referenced {
+        if let Some(Object::Dictionary(fonts)) = document.objects.get_mut(&id) {
+            remove_fonts(fonts, removed);
+        }
+    }
+}
+
+fn remove_fonts(fonts: &mut Dictionary, removed: &BTreeSet<ObjectId>) {
+    let names: Vec<Vec<u8>> = fonts
+        .iter()
+        .filter(|(_, font)| font.as_reference().is_ok_and(|id| removed.contains(&id)))
+        .map(|(name, _)| name.clone())
+        .collect();
+    for name in names {
+        let _removed = fonts.remove(&name);
+    }
+}
+
+/// The PostScript name of a font, which is how fonts that are not embedded are found.
+fn postscript_name(face: &Face<'_>) -> Option<Vec<u8>> {
+    face.names()
+        .into_iter()
+        .filter(|entry| entry.name_id == POST_SCRIPT_NAME)
+        .find_map(|entry| entry.to_string())
+        .map(String::into_bytes)
+}
+
+/// Draws the glyphs of every embedded TrueType font as vector outlines & removes the font files.
+/// With `keep_text`, the text stays as an invisible layer drawn with the fonts, which are then
+/// referenced by their PostScript names without being embedded.
+pub(crate) fn outline_text(document: &mut Document, keep_text: bool) -> Result<()> {
+    let page_ids: Vec<ObjectId> = document.get_pages().into_values().collect();
+    let mut files = HashMap::new();
+    for page_id in &page_ids {
+        for font_id in page_fonts(document, *page_id).into_values() {
+            if files.contains_key(&font_id) {
+                continue;
+            }
+            let Some((descriptor_id, file_id)) = font_file(document, font_id) else {
+                continue;
+            };
+            let stream = document.get_object(file_id)?.as_stream()?;
+            let data = if stream.dict.has(b"Filter") {
+                stream.decompressed_content()?
+            } else {
+                stream.content.clone()
+            };
+            let _previous = files.insert(font_id, (descriptor_id, data));
+        }
+    }
+    let faces: HashMap<ObjectId, Face<'_>> = files
+        .iter()
+        .filter_map(|(font_id, (_, data))| Some((*font_id, Face::parse(data, 0).ok()?)))
+        .collect();
+    for page_id in &page_ids {
+        let content = document.get_and_decode_page_content(*page_id)?;
+        let mut outliner = Outliner {
+            faces: &faces,
+            fonts: page_fonts(document, *page_id),
+            keep_text,
+            state: TextState::default(),
+            saved: Vec::new(),
+            text_matrix: IDENTITY,
+            line_matrix: IDENTITY,
+            outlines: Vec::new(),
+            operations: Vec::new(),
+        };
+        for operation in content.operations {
+            if outliner.process(&operation) {
+                outliner.operations.push(operation);
+            }
+        }
+        let operations = outliner.operations;
+        document.change_page_content(*page_id, Content { operations }.encode()?)?;
+        for content_id in document.get_page_contents(*page_id) {
+            document
+                .get_object_mut(content_id)?
+                .as_stream_mut()?
+                .compress()?;
+        }
+    }
+    let outlined: BTreeSet<ObjectId> = faces.keys().copied().collect();
+    if keep_text {
+        for (font_id, (descriptor_id, _)) in &files {
+            let _file = document
+                .get_object_mut(*descriptor_id)?
+                .as_dict_mut()?
+                .remove(b"FontFile2");
+            // A font that is not embedded is looked up by its name, which no longer names a subset
+            let postscript_name = faces.get(font_id).and_then(postscript_name);
+            rename_font(document, *font_id, *descriptor_id, |name| {
+                postscript_name
+                    .clone()
+                    .unwrap_or_else(|| untagged(name).to_vec())
+            })?;
+        }
+    } else {
+        remove_font_resources(document, &outlined);
+    }
+    let _pruned = document.prune_objects();
+    Ok(())
+}
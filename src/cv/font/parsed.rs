@@ -0,0 +1,17 @@
+//! Keeps the data of a TrueType font together with the face parsed from it, so each font is only
+//! parsed once when it is loaded.
+#![allow(
+    clippy::mem_forget,
+    reason = "self_cell forgets the data it moves into the cell it makes"
+)]
+
+use ttf_parser::Face;
+
+self_cell::self_cell!(
+    /// The data of a TrueType font & the face parsed from it.
+    pub(crate) struct ParsedFace {
+        owner: Vec<u8>,
+        #[covariant]
+        dependent: Face,
+    }
+);
@@ -0,0 +1,229 @@
+//! Finds the font files registered with `font` commands.
+//! Fonts are looked up by path, by file name in the font directories, or by their full name.
+//! Full names are matched against the file names first, as reading every font file is slow.
+
+use crate::style::command::{Font, FontFallback};
+use anyhow::{anyhow, Result};
+use std::cell::{OnceCell, RefCell};
+use std::collections::HashMap;
+use std::env;
+use std::fs::{read, read_dir};
+use std::path::{Path, PathBuf};
+use ttf_parser::name_id::{FAMILY, FULL_NAME, POST_SCRIPT_NAME, SUBFAMILY};
+use ttf_parser::Face;
+
+/// The directory searched for fonts before the system directories, relative to the working
+/// directory, where the Japanese fonts have always been placed.
+const LOCAL_FONT_DIRECTORY: &str = "fonts";
+/// The directories fonts are installed in on Linux, macOS & Windows.
+const SYSTEM_FONT_DIRECTORIES: [&str; 5] = [
+    "/usr/share/fonts",
+    "/usr/local/share/fonts",
+    "/Library/Fonts",
+    "/System/Library/Fonts",
+    "C:\\Windows\\Fonts",
+];
+/// The directories in the home directory fonts are installed in.
+const USER_FONT_DIRECTORIES: [&str; 3] = [".fonts", ".local/share/fonts", "Library/Fonts"];
+/// How deep to look into font directories, which group fonts in subdirectories.
+const MAX_SEARCH_DEPTH: usize = 8;
+/// The extensions of the font files that can be embedded.
+const FONT_EXTENSIONS: [&str; 2] = ["ttf", "otf"];
+/// How many font files to read when looking for a font by its full name, so a name that is
+/// nowhere to be found does not read every font of the system.
+const MAX_NAMED_FILES: usize = 2000;
+
+/// The full, PostScript, family & subfamily names of a font.
+type FontNames = Vec<(u16, String)>;
+
+/// The fonts registered so far, their fallbacks & the directories to look for them in.
+pub(crate) struct FontRegistry {
+    fonts: Vec<Font>,
+    fallbacks: Vec<FontFallback>,
+    directories: Vec<PathBuf>,
+    /// The font files in the directories, listed when first looked for.
+    files: OnceCell<Vec<PathBuf>>,
+    /// The names of the font files read so far.
+    names: RefCell<HashMap<PathBuf, FontNames>>,
+}
+
+impl FontRegistry {
+    pub(crate) fn new(directories: Vec<PathBuf>) -> Self {
+        FontRegistry {
+            fonts: Vec::new(),
+            fallbacks: Vec::new(),
+            directories,
+            files: OnceCell::new(),
+            names: RefCell::new(HashMap::new()),
+        }
+    }
+
+    /// Registers a font, replacing any font registered earlier under the same alias.
+    pub(crate) fn register(&mut self, font: Font) {
+        self.fonts
+            .retain(|registered| registered.alias != font.alias);
+        self.fonts.push(font);
+    }
+
+    /// Sets the fallbacks of a font face, replacing any set earlier.
+    pub(crate) fn set_fallback(&mut self, fallback: FontFallback) {
+        self.fallbacks
+            .retain(|registered| registered.face != fallback.face);
+        self.fallbacks.push(fallback);
+    }
+
+    pub(crate) fn fallbacks(&self) -> &[FontFallback] {
+        &self.fallbacks
+    }
+
+    pub(crate) fn add_directory(&mut self, directory: PathBuf) {
+        self.directories.push(directory);
+        self.files = OnceCell::new();
+    }
+
+    pub(crate) fn fonts(&self) -> &[Font] {
+        &self.fonts
+    }
+
+    /// The directories to look for fonts in, in order.
+    fn search_directories(&self) -> Vec<PathBuf> {
+        let mut directories = self.directories.clone();
+        directories.push(PathBuf::from(LOCAL_FONT_DIRECTORY));
+        if let Some(home) = env::var_os("HOME").or_else(|| env::var_os("USERPROFILE")) {
+            let home = PathBuf::from(home);
+            directories.extend(USER_FONT_DIRECTORIES.iter().map(|path| home.join(path)));
+        }
+        directories.extend(SYSTEM_FONT_DIRECTORIES.iter().map(PathBuf::from));
+        directories
+    }
+
+    /// The font files in the directories to look for fonts in.
+    fn files(&self) -> &[PathBuf] {
+        self.files.get_or_init(|| {
+            let mut files = Vec::new();
+            for directory in self.search_directories() {
+                collect_font_files(&directory, 0, &mut files);
+            }
+            files
+        })
+    }
+
+    /// Whether the full or PostScript name of the font in a file matches `name`.
+    /// A family name matches the regular style of the family.
+    #[allow(unused_results, reason = "every file is only read once")]
+    fn has_name(&self, path: &Path, name: &str) -> bool {
+        let mut cache = self.names.borrow_mut();
+        let names = cache
+            .entry(path.to_path_buf())
+            .or_insert_with(|| read_names(path));
+        let matches = |ids: &[u16], wanted: &str| {
+            names
+                .iter()
+                .any(|(id, value)| ids.contains(id) && value.eq_ignore_ascii_case(wanted))
+        };
+        matches(&[FULL_NAME, POST_SCRIPT_NAME], name)
+            || (matches(&[FAMILY], name) && matches(&[SUBFAMILY], "Regular"))
+    }
+
+    /// Finds the file of a registered font. The error lists every place that was looked in.
+    pub(crate) fn locate(&self, font: &Font) -> Result<PathBuf> {
+        let path = Path::new(&font.path);
+        let mut candidates = vec![path.to_path_buf()];
+        if !path.is_absolute() {
+            candidates.insert(0, font.directory.join(path));
+            candidates.dedup();
+        }
+        if let Some(candidate) = candidates.iter().find(|candidate| candidate.is_file()) {
+            return Ok(candidate.clone());
+        }
+        let files = self.files();
+        let by_file_name = path.file_name().and_then(|file_name| {
+            files
+                .iter()
+                .find(|file| file.file_name() == Some(file_name))
+        });
+        let wanted = simplify(&font.path);
+        let by_stem = || {
+            files.iter().find(|file| {
+                file.file_stem()
+                    .and_then(|stem| stem.to_str())
+                    .is_some_and(|stem| simplify(stem) == wanted)
+            })
+        };
+        let by_name = || {
+            files
+                .iter()
+                .take(MAX_NAMED_FILES)
+                .find(|file| self.has_name(file, &font.path))
+        };
+        by_file_name
+            .or_else(by_stem)
+            .or_else(by_name)
+            .cloned()
+            .ok_or_else(|| {
+                let searched: Vec<String> = candidates
+                    .iter()
+                    .map(|candidate| candidate.display().to_string())
+                    .chain(self.search_directories().iter().map(|directory| {
+                        let missing = if directory.is_dir() { "" } else { " (missing)" };
+                        format!("{}/**{missing}", directory.display())
+                    }))
+                    .collect();
+                anyhow!(
+                    "Failed to find the font {} for {}. Searched:\n  {}\n\
+                     Install the font, or point to it with a font command or --font-dir",
+                    font.path,
+                    font.alias,
+                    searched.join("\n  ")
+                )
+            })
+    }
+}
+
+/// Lowercases a font or file name & leaves out everything but letters & digits,
+/// so `DejaVu Serif` matches `DejaVuSerif.ttf`.
+fn simplify(name: &str) -> String {
+    name.chars()
+        .filter(char::is_ascii_alphanumeric)
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+/// Lists the font files in a directory & its subdirectories.
+fn collect_font_files(directory: &Path, depth: usize, files: &mut Vec<PathBuf>) {
+    let Ok(entries) = read_dir(directory) else {
+        return;
+    };
+    let mut entries: Vec<PathBuf> = entries.flatten().map(|entry| entry.path()).collect();
+    entries.sort();
+    for path in entries {
+        if path.is_dir() {
+            if depth < MAX_SEARCH_DEPTH {
+                collect_font_files(&path, depth + 1, files);
+            }
+            continue;
+        }
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(str::to_ascii_lowercase);
+        if extension.is_some_and(|extension| FONT_EXTENSIONS.contains(&extension.as_str())) {
+            files.push(path);
+        }
+    }
+}
+
+/// Reads the names of the font in a file, which has none if it cannot be read.
+fn read_names(path: &Path) -> FontNames {
+    let Ok(data) = read(path) else {
+        return Vec::new();
+    };
+    let Ok(face) = Face::parse(&data, 0) else {
+        return Vec::new();
+    };
+    face.names()
+        .into_iter()
+        .filter(|entry| [FULL_NAME, POST_SCRIPT_NAME, FAMILY, SUBFAMILY].contains(&entry.name_id))
+        .filter_map(|entry| Some((entry.name_id, entry.to_string()?)))
+        .collect()
+}
@@ -0,0 +1,394 @@
+//! Shrinks the embedded TrueType fonts down to the glyphs drawn on the pages.
+//! Glyph IDs are kept as they are, so the text written by printpdf stays valid, while the
+//! `ToUnicode` maps are rewritten from the text each glyph was drawn for.
+
+use anyhow::{anyhow, Result};
+use printpdf::lopdf::content::Operation;
+use printpdf::lopdf::{Dictionary, Document, Object, ObjectId, Stream};
+use printpdf::IndirectFontRef;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt::Write;
+use subsetter::Profile;
+use ttf_parser::{Face, GlyphId};
+
+/// The number of letters in the tag that marks the name of a subset font.
+const SUBSET_TAG_LENGTH: usize = 6;
+/// The most mappings a block of a `ToUnicode` map may have.
+const CMAP_BLOCK_SIZE: usize = 100;
+
+/// The fonts of a page by the names the page contents use for them.
+pub(crate) fn page_fonts(document: &Document, page_id: ObjectId) -> HashMap<Vec<u8>, ObjectId> {
+    let (inline, resource_ids) = document.get_page_resources(page_id);
+    let dictionaries = inline.into_iter().chain(
+        resource_ids
+            .into_iter()
+            .filter_map(|id| document.get_dictionary(id).ok()),
+    );
+    let mut fonts = HashMap::new();
+    for resources in dictionaries {
+        let Some(font_dictionary) = resources
+            .get(b"Font")
+            .ok()
+            .and_then(|entry| document.dereference(entry).ok())
+            .and_then(|(_, entry)| entry.as_dict().ok())
+        else {
+            continue;
+        };
+        for (name, font) in font_dictionary {
+            if let Ok(id) = font.as_reference() {
+                // Resources closer to the page take precedence
+                let _existing = fonts.entry(name.clone()).or_insert(id);
+            }
+        }
+    }
+    fonts
+}
+
+/// The strings of glyphs shown by a text operator.
+fn shown_strings(operation: &Operation) -> Vec<&[u8]> {
+    let operands: Vec<&Object> = match operation.operator.as_str() {
+        "Tj" | "'" | "\"" => operation.operands.last().into_iter().collect(),
+        "TJ" => operation
+            .operands
+            .first()
+            .and_then(|items| items.as_array().ok())
+            .map(|items| items.iter().collect())
+            .unwrap_or_default(),
+        _ => Vec::new(),
+    };
+    operands
+        .into_iter()
+        .filter_map(|operand| operand.as_str().ok())
+        .collect()
+}
+
+/// Collects the glyphs drawn with each font, from the two byte codes of the page contents.
+fn used_glyphs(document: &Document) -> Result<HashMap<ObjectId, BTreeSet<u16>>> {
+    let mut glyphs: HashMap<ObjectId, BTreeSet<u16>> = HashMap::new();
+    for page_id in document.get_pages().into_values() {
+        let fonts = page_fonts(document, page_id);
+        let content = document.get_and_decode_page_content(page_id)?;
+        let mut current_font = None;
+        for operation in &content.operations {
+            if operation.operator == "Tf" {
+                current_font = operation
+                    .operands
+                    .first()
+                    .and_then(|name| name.as_name().ok())
+                    .and_then(|name| fonts.get(name))
+                    .copied();
+                continue;
+            }
+            let Some(font_id) = current_font else {
+                continue;
+            };
+            for string in shown_strings(operation) {
+                glyphs.entry(font_id).or_default().extend(
+                    string
+                        .chunks_exact(2)
+                        .filter_map(|code| Some(u16::from_be_bytes(code.try_into().ok()?))),
+                );
+            }
+        }
+    }
+    Ok(glyphs)
+}
+
+/// Makes the tag that prefixes the names of subset fonts, from the font's name & glyphs.
+fn subset_tag(name: &[u8], glyphs: &BTreeSet<u16>) -> Result<String> {
+    // FNV-1a, so the same subset always gets the same tag
+    let mut hash = name
+        .iter()
+        .map(|byte| u32::from(*byte))
+        .chain(glyphs.iter().map(|glyph| u32::from(*glyph)))
+        .fold(0x811c_9dc5_u32, |hash, value| {
+            (hash ^ value).wrapping_mul(0x0100_0193_u32)
+        });
+    let mut tag = String::with_capacity(SUBSET_TAG_LENGTH + 1);
+    for _ in 0..SUBSET_TAG_LENGTH {
+        tag.push(char::from(b'A' + u8::try_from(hash.rem_euclid(26))?));
+        hash = hash.div_euclid(26);
+    }
+    tag.push('+');
+    Ok(tag)
+}
+
+/// Writes a `ToUnicode` map from glyphs to their text as UTF-16, so characters outside the
+/// Basic Multilingual Plane & variation sequences are copied out of the PDF as they were written.
+fn to_unicode_cmap(glyphs: &BTreeMap<u16, String>) -> Result<Vec<u8>> {
+    let mut cmap = String::from(
+        "/CIDInit /ProcSet findresource begin\n12 dict begin\nbegincmap\n\
+         /CIDSystemInfo << /Registry (Adobe) /Ordering (UCS) /Supplement 0 >> def\n\
+         /CMapName /Adobe-Identity-UCS def\n/CMapType 2 def\n\
+         1 begincodespacerange\n<0000> <FFFF>\nendcodespacerange\n",
+    );
+    let entries: Vec<(&u16, &String)> = glyphs.iter().collect();
+    for block in entries.chunks(CMAP_BLOCK_SIZE) {
+        writeln!(cmap, "{} beginbfchar", block.len())?;
+        for (glyph, text) in block {
+            write!(cmap, "<{glyph:04X}> <")?;
+            for unit in text.encode_utf16() {
+                write!(cmap, "{unit:04X}")?;
+            }
+            cmap.push_str(">\n");
+        }
+        cmap.push_str("endbfchar\n");
+    }
+    cmap.push_str("endcmap\nCMapName currentdict /CMap defineresource pop\nend\nend\n");
+    Ok(cmap.into_bytes())
+}
+
+/// Replaces the `ToUnicode` map of a font.
+fn replace_to_unicode(document: &mut Document, font_id: ObjectId, cmap: Vec<u8>) -> Result<()> {
+    let mut stream = Stream::new(Dictionary::new(), cmap);
+    stream.compress()?;
+    let existing = document
+        .get_dictionary(font_id)?
+        .get(b"ToUnicode")
+        .and_then(Object::as_reference)
+        .ok();
+    if let Some(id) = existing {
+        let _previous = document.objects.insert(id, Object::Stream(stream));
+    } else {
+        let id = document.add_object(stream);
+        document
+            .get_object_mut(font_id)?
+            .as_dict_mut()?
+            .set("ToUnicode", id);
+    }
+    Ok(())
+}
+
+/// The name of a font without its subset tag, if it has one.
+pub(super) fn untagged(name: &[u8]) -> &[u8] {
+    match name.split_at_checked(SUBSET_TAG_LENGTH) {
+        Some((tag, rest)) if tag.iter().all(u8::is_ascii_uppercase) && rest.starts_with(b"+") => {
+            rest.get(1..).unwrap_or_default()
+        }
+        _ => name,
+    }
+}
+
+/// Replaces a font name with the one `rename` makes from it.
+fn rename(dictionary: &mut Dictionary, key: &[u8], rename: &impl Fn(&[u8]) -> Vec<u8>) {
+    if let Ok(name) = dictionary.get(key).and_then(Object::as_name) {
+        let renamed = rename(name);
+        dictionary.set(key.to_vec(), Object::Name(renamed));
+    }
+}
+
+/// The descendant font of a composite font, which may be stored in the font itself.
+fn descendant(document: &Document, font_id: ObjectId) -> Option<&Dictionary> {
+    let font = document.get_dictionary(font_id).ok()?;
+    if font.get(b"Subtype").and_then(Object::as_name).ok()? != b"Type0" {
+        return None;
+    }
+    let first = font
+        .get(b"DescendantFonts")
+        .and_then(|descendants| document.dereference(descendants))
+        .ok()?
+        .1
+        .as_array()
+        .ok()?
+        .first()?;
+    document.dereference(first).ok()?.1.as_dict().ok()
+}
+
+/// Finds the font descriptor & font file of a composite font with an embedded TrueType font.
+pub(super) fn font_file(document: &Document, font_id: ObjectId) -> Option<(ObjectId, ObjectId)> {
+    let descriptor_id = descendant(document, font_id)?
+        .get(b"FontDescriptor")
+        .and_then(Object::as_reference)
+        .ok()?;
+    let file_id = document
+        .get_dictionary(descriptor_id)
+        .and_then(|descriptor| descriptor.get(b"FontFile2"))
+        .and_then(Object::as_reference)
+        .ok()?;
+    Some((descriptor_id, file_id))
+}
+
+/// Renames a composite font, its descendant & its descriptor with the name `rename` makes from
+/// each of their names.
+pub(super) fn rename_font(
+    document: &mut Document,
+    font_id: ObjectId,
+    descriptor_id: ObjectId,
+    rename_with: impl Fn(&[u8]) -> Vec<u8>,
+) -> Result<()> {
+    let font = document.get_object_mut(font_id)?.as_dict_mut()?;
+    rename(font, b"BaseFont", &rename_with);
+    let descendant_id = match font.get_mut(b"DescendantFonts")? {
+        Object::Array(descendants) => match descendants.first_mut() {
+            Some(Object::Dictionary(descendant)) => {
+                rename(descendant, b"BaseFont", &rename_with);
+                None
+            }
+            Some(Object::Reference(id)) => Some(*id),
+            _ => None,
+        },
+        Object::Reference(id) => {
+            let id = *id;
+            let descendants = document.get_object(id)?.as_array()?;
+            descendants
+                .first()
+                .and_then(|first| first.as_reference().ok())
+        }
+        _ => None,
+    };
+    if let Some(id) = descendant_id {
+        rename(
+            document.get_object_mut(id)?.as_dict_mut()?,
+            b"BaseFont",
+            &rename_with,
+        );
+    }
+    rename(
+        document.get_object_mut(descriptor_id)?.as_dict_mut()?,
+        b"FontName",
+        &rename_with,
+    );
+    Ok(())
+}
+
+/// Replaces every embedded TrueType font with a subset of the glyphs drawn with it,
+/// mapping the glyphs back to the text they were drawn for.
+/// Fonts that are never drawn keep only the glyph for missing characters.
+/// The text drawn with each font is found by the name the pages use for it, which is the name of
+/// its reference.
+pub(crate) fn subset_fonts(
+    document: &mut Document,
+    drawn_text: &BTreeMap<IndirectFontRef, BTreeMap<u16, String>>,
+) -> Result<()> {
+    let mut glyphs = used_glyphs(document)?;
+    let font_names: BTreeMap<ObjectId, Vec<u8>> = document
+        .get_pages()
+        .into_values()
+        .flat_map(|page_id| page_fonts(document, page_id))
+        .map(|(name, id)| (id, name))
+        .collect();
+    for (font_id, font_name) in font_names {
+        let Some((descriptor_id, file_id)) = font_file(document, font_id) else {
+            continue;
+        };
+        let glyphs = glyphs.remove(&font_id).unwrap_or_default();
+        let stream = document.get_object(file_id)?.as_stream()?;
+        let data = if stream.dict.has(b"Filter") {
+            stream.decompressed_content()?
+        } else {
+            stream.content.clone()
+        };
+        let reference = IndirectFontRef::new(String::from_utf8_lossy(&font_name));
+        if let Some(drawn) = drawn_text.get(&reference) {
+            let mapped = drawn
+                .iter()
+                .filter(|(glyph, _)| glyphs.contains(glyph))
+                .map(|(glyph, text)| (*glyph, text.clone()))
+                .collect();
+            replace_to_unicode(document, font_id, to_unicode_cmap(&mapped)?)?;
+        }
+        let kept: Vec<u16> = glyphs.iter().copied().collect();
+        let subset = subsetter::subset(&data, 0, Profile::pdf(&kept))
+            .map_err(|error| anyhow!("Failed to subset a font: {error}"))?;
+        let mut file = Stream::new(Dictionary::new(), subset);
+        file.dict.set("Length1", i64::try_from(file.content.len())?);
+        file.compress()?;
+        let _previous = document.objects.insert(file_id, Object::Stream(file));
+        let name = document
+            .get_dictionary(font_id)?
+            .get(b"BaseFont")
+            .and_then(Object::as_name)
+            .map(<[u8]>::to_vec)
+            .unwrap_or_default();
+        let tag = subset_tag(&name, &glyphs)?;
+        rename_font(document, font_id, descriptor_id, |untagged_name| {
+            [tag.as_bytes(), untagged_name].concat()
+        })?;
+    }
+    Ok(())
+}
+
+/// Adds the set of glyphs each subset font contains to its descriptor, as PDF/A-1 requires.
+/// The glyph IDs are used as they are, so the set holds the glyphs drawn, the glyph for missing
+/// characters & the glyphs the subset keeps as parts of others.
+pub(crate) fn add_cid_sets(document: &mut Document) -> Result<()> {
+    let glyphs = used_glyphs(document)?;
+    let font_ids: BTreeSet<ObjectId> = document
+        .get_pages()
+        .into_values()
+        .flat_map(|page_id| page_fonts(document, page_id).into_values())
+        .collect();
+    for font_id in font_ids {
+        let Some((descriptor_id, file_id)) = font_file(document, font_id) else {
+            continue;
+        };
+        let stream = document.get_object(file_id)?.as_stream()?;
+        let data = if stream.dict.has(b"Filter") {
+            stream.decompressed_content()?
+        } else {
+            stream.content.clone()
+        };
+        let face = Face::parse(&data, 0)?;
+        let drawn = glyphs.get(&font_id);
+        let mut set = Vec::new();
+        for glyph in 0..face.number_of_glyphs() {
+            let kept = glyph == 0
+                || drawn.is_some_and(|drawn| drawn.contains(&glyph))
+                || face.glyph_bounding_box(GlyphId(glyph)).is_some();
+            if kept {
+                let byte = usize::from(glyph.div_euclid(8));
+                if set.len() <= byte {
+                    set.resize(byte + 1, 0_u8);
+                }
+                if let Some(bits) = set.get_mut(byte) {
+                    // The first glyph is the highest bit
+                    *bits |= 0x80 >> glyph.rem_euclid(8);
+                }
+            }
+        }
+        let mut cid_set = Stream::new(Dictionary::new(), set);
+        cid_set.compress()?;
+        let cid_set_id = document.add_object(cid_set);
+        document
+            .get_object_mut(descriptor_id)?
+            .as_dict_mut()?
+            .set("CIDSet", cid_set_id);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn subset_tags_are_six_capitals() -> Result<()> {
+        let tag = subset_tag(b"F0", &BTreeSet::from([3, 36, 37]))?;
+        assert!(tag.len() == SUBSET_TAG_LENGTH + 1);
+        assert!(tag.ends_with('+'));
+        assert!(tag
+            .chars()
+            .take(SUBSET_TAG_LENGTH)
+            .all(|c| c.is_ascii_uppercase()));
+        Ok(())
+    }
+
+    #[test]
+    fn subset_tags_depend_on_the_font_and_glyphs() -> Result<()> {
+        let glyphs = BTreeSet::from([3, 36, 37]);
+        let tag = subset_tag(b"F0", &glyphs)?;
+        assert!(subset_tag(b"F0", &glyphs)? == tag);
+        assert!(subset_tag(b"F1", &glyphs)? != tag);
+        assert!(subset_tag(b"F0", &BTreeSet::from([3, 36]))? != tag);
+        Ok(())
+    }
+
+    #[test]
+    fn untagged_names_lose_only_the_subset_tag() -> Result<()> {
+        let tag = subset_tag(b"F0", &BTreeSet::from([3, 36, 37]))?;
+        assert!(untagged([tag.as_bytes(), b"F0"].concat().as_slice()) == b"F0");
+        assert!(untagged(b"IPAexMincho") == b"IPAexMincho");
+        assert!(untagged(b"Abcdef+F0") == b"Abcdef+F0");
+        Ok(())
+    }
+}
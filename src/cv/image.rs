@@ -0,0 +1,173 @@
+//! Loads raster images & draws them on the page.
+
+use super::resources::Resources;
+use crate::style::core::{Fit, Point, Size};
+use anyhow::{anyhow, Context, Result};
+use exif::{In, Tag};
+use printpdf::image_crate::io::Reader;
+use printpdf::image_crate::DynamicImage;
+use printpdf::lopdf::content::Operation;
+use printpdf::lopdf::Object;
+use printpdf::{Mm, PdfLayerReference, Pt};
+use std::fs::{self, File};
+use std::io::BufReader;
+use std::path::Path;
+
+/// Reads the EXIF orientation of an image, if it has one.
+fn read_orientation(path: &Path) -> Option<u32> {
+    let file = File::open(path).ok()?;
+    let exif = exif::Reader::new()
+        .read_from_container(&mut BufReader::new(file))
+        .ok()?;
+    exif.get_field(Tag::Orientation, In::PRIMARY)?
+        .value
+        .get_uint(0)
+}
+
+/// Rotates & flips an image so it is displayed the way the camera was held.
+fn apply_orientation(image: DynamicImage, orientation: u32) -> DynamicImage {
+    match orientation {
+        2 => image.fliph(),
+        3 => image.rotate180(),
+        4 => image.flipv(),
+        5 => image.rotate90().fliph(),
+        6 => image.rotate90(),
+        7 => image.rotate270().fliph(),
+        8 => image.rotate270(),
+        _ => image,
+    }
+}
+
+/// Decodes a JPEG, PNG, WebP, BMP or TIFF image, detecting the format from its contents.
+/// The image is turned upright according to its EXIF orientation.
+pub(crate) fn decode_image(path: &Path) -> Result<DynamicImage> {
+    let image = Reader::open(path)
+        .with_context(|| format!("Failed to open the image {}", path.display()))?
+        .with_guessed_format()
+        .with_context(|| format!("Failed to read the image {}", path.display()))?
+        .decode()
+        .map_err(|error| anyhow!("Failed to decode the image {}: {error}", path.display()))?;
+    Ok(match read_orientation(path) {
+        Some(orientation) => apply_orientation(image, orientation),
+        None => image,
+    })
+}
+
+/// The precision, width, height & number of components of a baseline or progressive JPEG file,
+/// read from its frame header.
+fn jpeg_frame(data: &[u8]) -> Option<(u8, u32, u32, u8)> {
+    let mut rest = data.strip_prefix(&[0xFF, 0xD8])?;
+    loop {
+        let [0xFF, marker, length_high, length_low, ..] = *rest else {
+            return None;
+        };
+        let end = 2 + usize::from(u16::from_be_bytes([length_high, length_low]));
+        if matches!(marker, 0xC0..=0xC2) {
+            let [precision, height_high, height_low, width_high, width_low, components, ..] =
+                *rest.get(4..end)?
+            else {
+                return None;
+            };
+            let height = u16::from_be_bytes([height_high, height_low]);
+            let width = u16::from_be_bytes([width_high, width_low]);
+            return Some((precision, width.into(), height.into(), components));
+        }
+        rest = rest.get(end..)?;
+    }
+}
+
+/// The JPEG file an image was decoded from, if the image still has the pixels of the file,
+/// so it can be embedded without being encoded again.
+fn jpeg_source(path: &Path, image: &DynamicImage) -> Option<Vec<u8>> {
+    let contents = fs::read(path).ok()?;
+    let frame = jpeg_frame(&contents)?;
+    // Turned & cropped images differ from the file, as do the colors of CMYK files
+    let upright = read_orientation(path).is_none_or(|orientation| orientation == 1);
+    let components = if image.color().has_color() { 3 } else { 1 };
+    (upright && frame == (8, image.width(), image.height(), components)).then_some(contents)
+}
+
+/// Crops an image around its center to the aspect ratio of the frame.
+#[allow(
+    clippy::as_conversions,
+    clippy::cast_possible_truncation,
+    clippy::cast_sign_loss,
+    reason = "the cropped size is positive & no larger than the image"
+)]
+fn crop_to_frame(image: &DynamicImage, frame: Size) -> DynamicImage {
+    let (width, height) = (f64::from(image.width()), f64::from(image.height()));
+    let frame_ratio = frame.width / frame.height;
+    let (cropped_width, cropped_height) = if width / height > frame_ratio {
+        ((height * frame_ratio).round(), height)
+    } else {
+        (width, (width / frame_ratio).round())
+    };
+    let x = ((width - cropped_width) / 2.0_f64) as u32;
+    let y = ((height - cropped_height) / 2.0_f64) as u32;
+    image.crop_imm(x, y, cropped_width as u32, cropped_height as u32)
+}
+
+/// Fits an image into a frame, returning the image to draw & the size to draw it at.
+pub(crate) fn fit_image(image: DynamicImage, frame: Size, fit: Fit) -> (DynamicImage, Size) {
+    match fit {
+        Fit::Stretch => (image, frame),
+        Fit::Cover => (crop_to_frame(&image, frame), frame),
+        Fit::Contain => {
+            let scale = (frame.width.0 / f64::from(image.width()))
+                .min(frame.height.0 / f64::from(image.height()));
+            let size = Size {
+                width: Mm(f64::from(image.width()) * scale),
+                height: Mm(f64::from(image.height()) * scale),
+            };
+            (image, size)
+        }
+    }
+}
+
+/// Makes what is drawn next in the graphics state translucent, if an opacity is given.
+pub(crate) fn apply_opacity(
+    opacity: Option<f64>,
+    resources: &Resources,
+    layer: &PdfLayerReference,
+) {
+    if let Some(opacity) = opacity {
+        let state = resources.add_opacity(opacity);
+        layer.add_operation(Operation::new("gs", vec![Object::Name(state.into_bytes())]));
+    }
+}
+
+/// Draws an image loaded from `path` with its bottom left corner at `position`, scaled to `size`.
+pub(crate) fn place_image(
+    image: &DynamicImage,
+    path: &Path,
+    position: Point,
+    size: Size,
+    opacity: Option<f64>,
+    resources: &Resources,
+    layer: &PdfLayerReference,
+) -> Result<()> {
+    let name = resources.add_image(
+        image,
+        jpeg_source(path, image),
+        size,
+        &path.display().to_string(),
+    )?;
+    layer.save_graphics_state();
+    apply_opacity(opacity, resources, layer);
+    // Images are drawn into a unit square, so scale it to the wanted size
+    let matrix = [
+        Pt::from(size.width).0,
+        0.0_f64,
+        0.0_f64,
+        Pt::from(size.height).0,
+        Pt::from(position.x).0,
+        Pt::from(position.y).0,
+    ];
+    layer.add_operation(Operation::new(
+        "cm",
+        matrix.into_iter().map(Object::Real).collect(),
+    ));
+    layer.add_operation(Operation::new("Do", vec![Object::Name(name.into_bytes())]));
+    layer.restore_graphics_state();
+    Ok(())
+}
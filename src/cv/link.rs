@@ -0,0 +1,232 @@
+//! Collects clickable links while the CV is drawn & adds them to the finished PDF as annotations.
+
+use super::markup::{Span, SpanStyle};
+use anyhow::{anyhow, Result};
+use printpdf::lopdf::{dictionary, Document, Object};
+use printpdf::{Mm, Pt};
+use std::cell::{Cell, RefCell};
+
+/// The punctuation that is left out of a link at the end of an email or URL.
+const TRAILING_PUNCTUATION: [char; 7] = ['.', ',', ';', ':', ')', '。', '、'];
+
+/// An area of a page that opens a link when clicked.
+struct Annotation {
+    /// The index of the page the area is on.
+    page: usize,
+    left: Mm,
+    bottom: Mm,
+    right: Mm,
+    top: Mm,
+    /// A URI, or `#` followed by a page number to link within the document.
+    target: String,
+}
+
+/// The links added to the document so far.
+pub(crate) struct Links {
+    /// Whether to turn emails & URLs found in values into links.
+    autolink: bool,
+    /// Whether to underline the links in values.
+    underline: bool,
+    page: Cell<usize>,
+    annotations: RefCell<Vec<Annotation>>,
+}
+
+impl Links {
+    pub(crate) fn new(autolink: bool, underline: bool) -> Self {
+        Links {
+            autolink,
+            underline,
+            page: Cell::new(0),
+            annotations: RefCell::new(Vec::new()),
+        }
+    }
+
+    /// Whether links in values are underlined.
+    pub(crate) fn underlined(&self) -> bool {
+        self.underline
+    }
+
+    /// Moves on to adding links to the next page.
+    pub(crate) fn next_page(&self) {
+        self.page.set(self.page.get() + 1);
+    }
+
+    /// Makes the area between the corners open `target` when clicked.
+    pub(crate) fn add(&self, bottom_left: (Mm, Mm), top_right: (Mm, Mm), target: &str) {
+        let (left, bottom) = bottom_left;
+        let (right, top) = top_right;
+        self.annotations.borrow_mut().push(Annotation {
+            page: self.page.get(),
+            left,
+            bottom,
+            right,
+            top,
+            target: target.to_owned(),
+        });
+    }
+
+    /// Turns emails & URLs in the plain text of the spans into links, if enabled.
+    pub(crate) fn autolink(&self, spans: Vec<Span>) -> Vec<Span> {
+        if !self.autolink {
+            return spans;
+        }
+        let mut linked = Vec::new();
+        for span in spans {
+            if span.style != SpanStyle::Plain || span.link.is_some() {
+                linked.push(span);
+                continue;
+            }
+            let mut plain = String::new();
+            for word in span.text.split_inclusive(char::is_whitespace) {
+                let trimmed = word.trim_end_matches(|c: char| {
+                    c.is_whitespace() || TRAILING_PUNCTUATION.contains(&c)
+                });
+                let Some(target) = auto_target(trimmed) else {
+                    plain.push_str(word);
+                    continue;
+                };
+                if !plain.is_empty() {
+                    linked.push(Span {
+                        text: std::mem::take(&mut plain),
+                        style: SpanStyle::Plain,
+                        link: None,
+                    });
+                }
+                linked.push(Span {
+                    text: trimmed.to_owned(),
+                    style: SpanStyle::Plain,
+                    link: Some(target),
+                });
+                plain.push_str(word.get(trimmed.len()..).unwrap_or_default());
+            }
+            if !plain.is_empty() {
+                linked.push(Span {
+                    text: plain,
+                    style: SpanStyle::Plain,
+                    link: None,
+                });
+            }
+        }
+        linked
+    }
+
+    /// Adds the collected links to the pages of a saved PDF.
+    pub(crate) fn add_to_document(&self, document: &mut Document) -> Result<()> {
+        let annotations = self.annotations.borrow();
+        let pages = document.get_pages();
+        for annotation in annotations.iter() {
+            let page_number = u32::try_from(annotation.page + 1)?;
+            let page_id = *pages
+                .get(&page_number)
+                .ok_or_else(|| anyhow!("Failed to find page {page_number} to add a link to"))?;
+            let action = if let Some(destination) = annotation.target.strip_prefix('#') {
+                let destination_number = destination.parse::<u32>()?;
+                let destination_id = *pages.get(&destination_number).ok_or_else(|| {
+                    anyhow!("Failed to find page {destination_number} to link to")
+                })?;
+                dictionary! {
+                    "S" => "GoTo",
+                    "D" => vec![destination_id.into(), "Fit".into()],
+                }
+            } else {
+                dictionary! {
+                    "S" => "URI",
+                    "URI" => Object::string_literal(encode_uri(&annotation.target)),
+                }
+            };
+            let rect: Vec<Object> = [
+                annotation.left,
+                annotation.bottom,
+                annotation.right,
+                annotation.top,
+            ]
+            .into_iter()
+            .map(|position| Object::Real(Pt::from(position).0))
+            .collect();
+            let annotation_id = document.add_object(dictionary! {
+                "Type" => "Annot",
+                "Subtype" => "Link",
+                "Rect" => rect,
+                "Border" => vec![Object::Integer(0); 3],
+                "A" => action,
+            });
+            let page = document.get_object_mut(page_id)?.as_dict_mut()?;
+            if let Ok(existing) = page.get_mut(b"Annots").and_then(Object::as_array_mut) {
+                existing.push(annotation_id.into());
+            } else {
+                page.set("Annots", vec![annotation_id.into()]);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The link target for a word that looks like an email or URL.
+fn auto_target(word: &str) -> Option<String> {
+    if word.starts_with("http://") || word.starts_with("https://") {
+        return Some(word.to_owned());
+    }
+    if word.starts_with("www.") {
+        return Some(format!("https://{word}"));
+    }
+    let (user, domain) = word.split_once('@')?;
+    let (host, top_level) = domain.rsplit_once('.')?;
+    if user.is_empty() || host.is_empty() || top_level.is_empty() || domain.contains('@') {
+        return None;
+    }
+    Some(format!("mailto:{word}"))
+}
+
+/// Percent-encodes the bytes of a URI that aren't printable ASCII, as a URI in a PDF has to be
+/// 7-bit ASCII.
+fn encode_uri(target: &str) -> String {
+    target
+        .bytes()
+        .map(|byte| {
+            if byte.is_ascii_graphic() {
+                char::from(byte).to_string()
+            } else {
+                format!("%{byte:02X}")
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn urls_link_to_themselves() {
+        assert!(auto_target("https://example.com").as_deref() == Some("https://example.com"));
+        assert!(auto_target("www.example.com").as_deref() == Some("https://www.example.com"));
+    }
+
+    #[test]
+    fn emails_link_to_mailto() {
+        assert!(auto_target("a@example.com").as_deref() == Some("mailto:a@example.com"));
+    }
+
+    #[test]
+    fn other_words_are_not_linked() {
+        for word in [
+            "example.com",
+            "@example.com",
+            "a@example",
+            "a@b@c.com",
+            "a@.com",
+        ] {
+            assert!(auto_target(word).is_none());
+        }
+    }
+
+    #[test]
+    fn ascii_uris_are_kept() {
+        assert!(encode_uri("https://example.com/a?b=c#d") == "https://example.com/a?b=c#d");
+    }
+
+    #[test]
+    fn other_characters_are_percent_encoded() {
+        assert!(encode_uri("https://例.jp/a b") == "https://%E4%BE%8B.jp/a%20b");
+    }
+}
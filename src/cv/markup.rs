@@ -0,0 +1,444 @@
+//! Lays out inline rich text markup: `**bold**`, `*italic*`, `` `code` `` & `[text](url)` links.
+//! A backslash before a marker character keeps it as plain text.
+//! Markup is only drawn in commands that enable it with `markup=true`; otherwise every marker
+//! character is escaped, so values are drawn as written.
+
+use super::font::{
+    is_variation_selector, resolve_typeface, FaceRequest, FontMap, LoadedFont, Typeface,
+};
+use super::link::Links;
+use super::ruby::{draw_segments, parse_markup, segment_width, strip_markup, Segment};
+use crate::style::core::{Align, FontOptions, Point};
+use anyhow::{anyhow, Result};
+use printpdf::{Mm, PdfLayerReference};
+use std::borrow::Cow;
+
+/// The characters that can be escaped with a backslash.
+const ESCAPABLE: [char; 4] = ['\\', '*', '`', '['];
+
+/// The font alias code spans are set in when it is registered, instead of the surrounding font.
+const MONOSPACE_FACE: &str = "monospace";
+/// The characters a line is never wrapped before, such as closing brackets & small kana.
+const NO_BREAK_BEFORE: &str =
+    "、。，．・：；？！ー）」』】〕〉》ぁぃぅぇぉっゃゅょゎァィゥェォッャュョヮヵヶ";
+/// The characters a line is never wrapped after, such as opening brackets.
+const NO_BREAK_AFTER: &str = "（「『【〔〈《";
+
+/// How a span of text is styled.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(crate) enum SpanStyle {
+    Plain,
+    Bold,
+    Italic,
+    Code,
+}
+
+/// A run of text sharing the same style.
+pub(crate) struct Span {
+    pub(crate) text: String,
+    pub(crate) style: SpanStyle,
+    /// Where the span links to, if it is a link.
+    pub(crate) link: Option<String>,
+}
+
+/// Finds the end of a span that starts after `open` & finishes at `close`.
+fn find_closing<'a>(rest: &'a str, open: &str, close: &str) -> Option<(&'a str, usize)> {
+    let inner = rest.strip_prefix(open)?;
+    let end = inner.find(close)?;
+    if end == 0 {
+        return None;
+    }
+    Some((inner.get(..end)?, open.len() + end + close.len()))
+}
+
+/// Finds a `[text](url)` link at the start of `rest`.
+fn find_link(rest: &str) -> Option<(&str, &str, usize)> {
+    let (text, text_length) = find_closing(rest, "[", "](")?;
+    let after_text = rest.get(text_length..)?;
+    let end = after_text.find(')')?;
+    let url = after_text.get(..end)?;
+    Some((text, url, text_length + end + 1))
+}
+
+/// Finds a styled span or link at the start of `rest`, along with its length in the line.
+fn find_styled(rest: &str) -> Option<(&str, SpanStyle, Option<&str>, usize)> {
+    if let Some((text, url, length)) = find_link(rest) {
+        return Some((text, SpanStyle::Plain, Some(url), length));
+    }
+    find_closing(rest, "**", "**")
+        .map(|(text, length)| (text, SpanStyle::Bold, None, length))
+        .or_else(|| {
+            find_closing(rest, "*", "*")
+                .map(|(text, length)| (text, SpanStyle::Italic, None, length))
+        })
+        .or_else(|| {
+            find_closing(rest, "`", "`").map(|(text, length)| (text, SpanStyle::Code, None, length))
+        })
+}
+
+/// The length of a backslash & the marker character it escapes at the start of `rest`.
+fn find_escape(rest: &str) -> Option<usize> {
+    let escaped = rest.strip_prefix('\\')?.chars().next()?;
+    ESCAPABLE.contains(&escaped).then(|| 1 + escaped.len_utf8())
+}
+
+/// Escapes every marker character in a value of a command that does not enable markup,
+/// so it is drawn as written.
+pub(crate) fn markup_value<'v>(value: &'v str, options: &FontOptions) -> Cow<'v, str> {
+    if options.markup.unwrap_or_default() || !value.contains(ESCAPABLE) {
+        return Cow::Borrowed(value);
+    }
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if ESCAPABLE.contains(&c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    Cow::Owned(escaped)
+}
+
+/// Splits a line into styled spans.
+/// Markers without a matching closing marker are kept as plain text.
+pub(crate) fn parse_spans(line: &str) -> Vec<Span> {
+    let mut spans = Vec::new();
+    let mut plain = String::new();
+    let mut index = 0;
+    while let Some(rest) = line.get(index..) {
+        let Some(c) = rest.chars().next() else {
+            break;
+        };
+        if let Some((text, style, link, length)) = find_styled(rest) {
+            if !plain.is_empty() {
+                spans.push(Span {
+                    text: std::mem::take(&mut plain),
+                    style: SpanStyle::Plain,
+                    link: None,
+                });
+            }
+            spans.push(Span {
+                text: text.to_owned(),
+                style,
+                link: link.map(str::to_owned),
+            });
+            index += length;
+            continue;
+        }
+        if let Some(length) = find_escape(rest) {
+            plain.push_str(rest.get(1..length).unwrap_or_default());
+            index += length;
+            continue;
+        }
+        plain.push(c);
+        index += c.len_utf8();
+    }
+    if !plain.is_empty() {
+        spans.push(Span {
+            text: plain,
+            style: SpanStyle::Plain,
+            link: None,
+        });
+    }
+    spans
+}
+
+/// The length of `{base|ruby}` markup at the start of `rest`.
+fn find_ruby(rest: &str) -> Option<usize> {
+    let inner = rest.strip_prefix('{')?;
+    let end = inner.find('}')?;
+    inner.get(..end)?.contains('|').then_some(end + 2)
+}
+
+/// Whether a character is as wide as it is tall, like kana & kanji, so lines can wrap next to it.
+fn is_wide(c: char) -> bool {
+    matches!(
+        c,
+        '\u{3000}'..='\u{30FF}'
+            | '\u{3400}'..='\u{4DBF}'
+            | '\u{4E00}'..='\u{9FFF}'
+            | '\u{F900}'..='\u{FAFF}'
+            | '\u{FF00}'..='\u{FFEF}'
+    )
+}
+
+/// Whether a line can wrap between two characters: after spaces or next to wide characters,
+/// but not before closing or after opening punctuation.
+fn can_break(before: char, after: char) -> bool {
+    if after.is_whitespace() || NO_BREAK_BEFORE.contains(after) || NO_BREAK_AFTER.contains(before) {
+        return false;
+    }
+    before.is_whitespace() || is_wide(before) || is_wide(after)
+}
+
+/// The byte offsets a line can be wrapped at.
+/// Styled spans, links, ruby markup & escaped characters are never split.
+pub(crate) fn break_points(line: &str) -> Vec<usize> {
+    let mut points = Vec::new();
+    let mut previous: Option<char> = None;
+    let mut index = 0;
+    while let Some(rest) = line.get(index..) {
+        let Some(c) = rest.chars().next() else {
+            break;
+        };
+        let length = find_styled(rest)
+            .map(|(_, _, _, length)| length)
+            .or_else(|| find_ruby(rest))
+            .or_else(|| find_escape(rest))
+            .unwrap_or_else(|| c.len_utf8());
+        if previous.is_some_and(|before| can_break(before, c)) {
+            points.push(index);
+        }
+        let unit = rest.get(..length).unwrap_or_default();
+        previous = unit.chars().next_back();
+        index += length;
+    }
+    points
+}
+
+/// Picks the font to draw a span with, based on the surrounding text's font.
+pub(crate) fn span_typeface<'a>(
+    request: FaceRequest<'_>,
+    span: &Span,
+    fonts: &'a FontMap<'a>,
+) -> Result<Typeface<'a>> {
+    resolve_typeface(span_request(request, span, fonts), fonts)
+}
+
+/// The face to draw a span in, based on the surrounding text's face.
+fn span_request<'a>(request: FaceRequest<'a>, span: &Span, fonts: &FontMap<'_>) -> FaceRequest<'a> {
+    match span.style {
+        SpanStyle::Bold => FaceRequest {
+            bold: true,
+            ..request
+        },
+        SpanStyle::Italic => FaceRequest {
+            italic: true,
+            ..request
+        },
+        // Code is set in the font registered as monospace, if there is one
+        SpanStyle::Code if fonts.contains(MONOSPACE_FACE) => FaceRequest {
+            face: MONOSPACE_FACE,
+            ..request
+        },
+        SpanStyle::Plain | SpanStyle::Code => request,
+    }
+}
+
+/// The ruby annotated segments of a span. Code is always set as is.
+fn span_segments(span: &Span) -> Vec<Segment<'_>> {
+    if span.style == SpanStyle::Code {
+        vec![Segment {
+            base: &span.text,
+            ruby: None,
+        }]
+    } else {
+        parse_markup(&span.text)
+    }
+}
+
+/// Removes all markup from a line, leaving only the text that is drawn on the baseline.
+pub(crate) fn plain_text(line: &str) -> String {
+    parse_spans(line)
+        .iter()
+        .map(|span| {
+            if span.style == SpanStyle::Code {
+                span.text.clone()
+            } else {
+                strip_markup(&span.text)
+            }
+        })
+        .collect()
+}
+
+/// Measures the width of a line of rich text.
+/// Each style used in the line is resolved to a typeface once, however many spans have it.
+pub(crate) fn line_width<'a>(
+    line: &str,
+    request: FaceRequest<'_>,
+    fonts: &'a FontMap<'a>,
+    font_size: f64,
+) -> Result<Mm> {
+    let spans = parse_spans(line);
+    let mut typefaces: Vec<(SpanStyle, Typeface<'a>)> = Vec::new();
+    for span in &spans {
+        if !typefaces.iter().any(|(style, _)| *style == span.style) {
+            typefaces.push((span.style, span_typeface(request, span, fonts)?));
+        }
+    }
+    let mut width = Mm(0.0_f64);
+    for span in &spans {
+        let Some((_, typeface)) = typefaces.iter().find(|(style, _)| *style == span.style) else {
+            continue;
+        };
+        for segment in span_segments(span) {
+            width += segment_width(&segment, typeface, font_size)?;
+        }
+    }
+    Ok(width)
+}
+
+/// Works out where a line of rich text starts relative to its position & the letter spacing to
+/// draw it with, so it is aligned within `width`.
+/// Without a width, centered & right aligned lines are placed around & before their position.
+pub(crate) fn align_line<'a>(
+    line: &str,
+    align: Align,
+    width: Option<Mm>,
+    request: FaceRequest<'a>,
+    fonts: &FontMap<'_>,
+    font_size: f64,
+) -> Result<(Mm, FaceRequest<'a>)> {
+    let characters = plain_text(line)
+        .chars()
+        .filter(|c| !is_variation_selector(*c))
+        .count();
+    if align == Align::Left || characters == 0 {
+        return Ok((Mm(0.0_f64), request));
+    }
+    // The spacing after the last character is not seen
+    let measured = line_width(line, request, fonts, font_size)? - request.letter_spacing;
+    let Some(width) = width else {
+        return match align {
+            Align::Distribute => Err(anyhow!(
+                "Distributing {line} needs a width to spread it across, such as width=30"
+            )),
+            Align::Right => Ok((Mm(0.0_f64) - measured, request)),
+            Align::Left | Align::Center => Ok((Mm(0.0_f64) - measured / 2.0_f64, request)),
+        };
+    };
+    match align {
+        Align::Distribute if characters > 1 => {
+            let unspaced = FaceRequest {
+                letter_spacing: Mm(0.0_f64),
+                ..request
+            };
+            let natural = line_width(line, unspaced, fonts, font_size)?;
+            let gaps = f64::from(u32::try_from(characters - 1)?);
+            let spaced = FaceRequest {
+                letter_spacing: (width - natural) / gaps,
+                ..request
+            };
+            Ok((Mm(0.0_f64), spaced))
+        }
+        Align::Right => Ok((width - measured, request)),
+        // A single distributed character is centered
+        Align::Left | Align::Center | Align::Distribute => {
+            Ok(((width - measured) / 2.0_f64, request))
+        }
+    }
+}
+
+fn underline(
+    x: Mm,
+    width: Mm,
+    baseline: Mm,
+    font: &LoadedFont,
+    font_size: f64,
+    layer: &PdfLayerReference,
+) {
+    let y = baseline + font.descent(font_size) / 2.0_f64;
+    layer.save_graphics_state();
+    layer.set_outline_thickness(font_size / 20.0_f64);
+    layer.add_shape(printpdf::Line {
+        points: vec![
+            (printpdf::Point::new(x, y), false),
+            (printpdf::Point::new(x + width, y), false),
+        ],
+        is_closed: false,
+        has_fill: false,
+        has_stroke: true,
+        is_clipping_path: false,
+    });
+    layer.restore_graphics_state();
+}
+
+/// Draws a line of rich text with its baseline starting at `origin`.
+/// Returns the width of the drawn line.
+pub(crate) fn draw_rich_line(
+    line: &str,
+    origin: Point,
+    request: FaceRequest<'_>,
+    font_size: f64,
+    fonts: &FontMap<'_>,
+    links: &Links,
+    layer: &PdfLayerReference,
+) -> Result<Mm> {
+    let baseline = origin.y;
+    let mut x = origin.x;
+    for span in links.autolink(parse_spans(line)) {
+        let typeface = span_typeface(request, &span, fonts)?;
+        let width = draw_segments(
+            &span_segments(&span),
+            x,
+            baseline,
+            &typeface,
+            font_size,
+            layer,
+        )?;
+        if let Some(target) = &span.link {
+            if links.underlined() {
+                underline(x, width, baseline, typeface.font, font_size, layer);
+            }
+            links.add(
+                (x, baseline + typeface.font.descent(font_size)),
+                (x + width, baseline + typeface.font.ascent(font_size)),
+                target,
+            );
+        }
+        x += width;
+    }
+    Ok(x - origin.x)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The text, style & link of each span.
+    fn spans(line: &str) -> Vec<(String, SpanStyle, Option<String>)> {
+        parse_spans(line)
+            .into_iter()
+            .map(|span| (span.text, span.style, span.link))
+            .collect()
+    }
+
+    #[test]
+    fn styles_are_split_into_spans() {
+        assert!(
+            spans("a **b** *c* `d`")
+                == [
+                    ("a ".to_owned(), SpanStyle::Plain, None),
+                    ("b".to_owned(), SpanStyle::Bold, None),
+                    (" ".to_owned(), SpanStyle::Plain, None),
+                    ("c".to_owned(), SpanStyle::Italic, None),
+                    (" ".to_owned(), SpanStyle::Plain, None),
+                    ("d".to_owned(), SpanStyle::Code, None),
+                ]
+        );
+    }
+
+    #[test]
+    fn links_keep_their_target() {
+        assert!(
+            spans("see [docs](https://example.com)")
+                == [
+                    ("see ".to_owned(), SpanStyle::Plain, None),
+                    (
+                        "docs".to_owned(),
+                        SpanStyle::Plain,
+                        Some("https://example.com".to_owned())
+                    ),
+                ]
+        );
+    }
+
+    #[test]
+    fn escaped_characters_are_plain_text() {
+        assert!(spans(r"\*a\* \`b\`") == [("*a* `b`".to_owned(), SpanStyle::Plain, None)]);
+    }
+
+    #[test]
+    fn unclosed_markup_is_plain_text() {
+        assert!(spans("**a b `c") == [("**a b `c".to_owned(), SpanStyle::Plain, None)]);
+    }
+}
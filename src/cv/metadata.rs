@@ -0,0 +1,368 @@
+//! Describes the CV in the document information dictionary & a matching XMP metadata stream,
+//! so the PDF is indexed by its title, author, keywords & dates.
+
+use super::pdfa::Conformance;
+use super::report::warn;
+use anyhow::{anyhow, Result};
+use printpdf::lopdf::{dictionary, Document, Object, Stream, StringFormat};
+use sha2::{Digest, Sha256};
+use std::env;
+use std::fmt::Write;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The application named as the creator & producer of the PDF unless overridden.
+const APPLICATION: &str = concat!(env!("CARGO_PKG_NAME"), " ", env!("CARGO_PKG_VERSION"));
+/// The subject of the PDF unless overridden.
+const DEFAULT_SUBJECT: &str = "Curriculum vitae";
+const SECONDS_PER_DAY: i64 = 86_400;
+/// The environment variable that fixes the moment the PDF is made.
+const SOURCE_DATE_EPOCH: &str = "SOURCE_DATE_EPOCH";
+/// The bytes of the hash kept as the file identifier.
+const IDENTIFIER_LENGTH: usize = 16;
+
+/// A moment in UTC, to the second.
+#[derive(Clone, Copy)]
+pub(crate) struct Timestamp {
+    year: i64,
+    month: i64,
+    day: i64,
+    hour: i64,
+    minute: i64,
+    second: i64,
+}
+
+impl Timestamp {
+    /// The moment a number of seconds after the Unix epoch.
+    pub(crate) fn from_unix(seconds: i64) -> Self {
+        // Converts days to a date in the proleptic Gregorian calendar, counting years from March
+        // so leap days come last
+        let days = seconds.div_euclid(SECONDS_PER_DAY) + 719_468;
+        let time = seconds.rem_euclid(SECONDS_PER_DAY);
+        let era = days.div_euclid(146_097);
+        let day_of_era = days.rem_euclid(146_097);
+        let year_of_era = (day_of_era - day_of_era.div_euclid(1460)
+            + day_of_era.div_euclid(36_524)
+            - day_of_era.div_euclid(146_096))
+        .div_euclid(365);
+        let day_of_year = day_of_era
+            - (365 * year_of_era + year_of_era.div_euclid(4) - year_of_era.div_euclid(100));
+        let month_from_march = (5 * day_of_year + 2).div_euclid(153);
+        let month = if month_from_march < 10 {
+            month_from_march + 3
+        } else {
+            month_from_march - 9
+        };
+        Timestamp {
+            year: year_of_era + era * 400 + i64::from(month <= 2),
+            month,
+            day: day_of_year - (153 * month_from_march + 2).div_euclid(5) + 1,
+            hour: time.div_euclid(3600),
+            minute: time.rem_euclid(3600).div_euclid(60),
+            second: time.rem_euclid(60),
+        }
+    }
+
+    /// The current moment.
+    pub(crate) fn now() -> Result<Self> {
+        let seconds = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+        Ok(Self::from_unix(i64::try_from(seconds)?))
+    }
+
+    /// The moment set by `SOURCE_DATE_EPOCH` in seconds since the Unix epoch, which reproducible
+    /// builds use in place of the current moment.
+    pub(crate) fn source_date() -> Result<Option<Self>> {
+        let Some(seconds) = env::var_os(SOURCE_DATE_EPOCH) else {
+            return Ok(None);
+        };
+        let seconds = seconds.to_string_lossy();
+        let seconds = seconds.trim().parse().map_err(|error| {
+            anyhow!("Failed to read {SOURCE_DATE_EPOCH} {seconds} as seconds: {error}")
+        })?;
+        Ok(Some(Self::from_unix(seconds)))
+    }
+
+    /// Reads the first date in text such as `2024-04-01` or `2024年4月1日現在`, as midnight.
+    pub(crate) fn parse_date(text: &str) -> Option<Self> {
+        let mut numbers = text
+            .split(|c: char| !c.is_ascii_digit())
+            .filter(|number| !number.is_empty());
+        let year = numbers
+            .next()
+            .filter(|year| year.len() == 4)?
+            .parse()
+            .ok()?;
+        let month = numbers.next()?.parse().ok()?;
+        let day = numbers.next()?.parse().ok()?;
+        let days_in_month = match month {
+            2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+            2 => 28,
+            4 | 6 | 9 | 11 => 30,
+            1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+            _ => return None,
+        };
+        (1..=days_in_month).contains(&day).then_some(Timestamp {
+            year,
+            month,
+            day,
+            hour: 0,
+            minute: 0,
+            second: 0,
+        })
+    }
+
+    /// Formats the moment as a PDF date string, such as `D:20240401000000Z`.
+    fn pdf_date(&self) -> String {
+        format!(
+            "D:{:04}{:02}{:02}{:02}{:02}{:02}Z",
+            self.year, self.month, self.day, self.hour, self.minute, self.second
+        )
+    }
+
+    /// Formats the moment as an XMP date, such as `2024-04-01T00:00:00Z`.
+    fn xmp_date(&self) -> String {
+        format!(
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+            self.year, self.month, self.day, self.hour, self.minute, self.second
+        )
+    }
+}
+
+/// The metadata given on the command line, which takes precedence over the input file.
+#[derive(Default)]
+pub(crate) struct MetadataOptions {
+    pub(crate) title: Option<String>,
+    pub(crate) author: Option<String>,
+    pub(crate) subject: Option<String>,
+    pub(crate) keywords: Vec<String>,
+    pub(crate) creator: Option<String>,
+    /// The creation date, in any form the date in the input file may take.
+    pub(crate) date: Option<String>,
+}
+
+/// The metadata written to the PDF.
+pub(crate) struct Metadata {
+    title: String,
+    author: String,
+    subject: String,
+    keywords: Vec<String>,
+    creator: String,
+    created: Timestamp,
+    modified: Timestamp,
+    /// The PDF/A level the document conforms to, if any.
+    conformance: Option<Conformance>,
+}
+
+impl Metadata {
+    /// Fills in the metadata from the `name` & `date` of the input file, unless the command line
+    /// overrides them. Without a readable date, the PDF is dated when it is made, which is
+    /// `SOURCE_DATE_EPOCH` if set. A `deterministic` PDF is never dated by the clock, so it is
+    /// modified when it was created without `SOURCE_DATE_EPOCH`.
+    pub(crate) fn new(
+        options: &MetadataOptions,
+        name: &str,
+        date: &str,
+        conformance: Option<Conformance>,
+        deterministic: bool,
+    ) -> Result<Self> {
+        let created =
+            match &options.date {
+                Some(given) => Some(Timestamp::parse_date(given).ok_or_else(|| {
+                    anyhow!("Failed to read the date {given}, such as 2024-04-01")
+                })?),
+                None => Timestamp::parse_date(date),
+            };
+        let modified = match (Timestamp::source_date()?, created) {
+            (Some(source_date), _) => source_date,
+            (None, Some(created)) if deterministic => created,
+            (None, None) if deterministic => {
+                return Err(anyhow!(
+                    "Failed to date the deterministic PDF; set {SOURCE_DATE_EPOCH}, --date or \
+                     the date in the input file"
+                ))
+            }
+            (None, _) => Timestamp::now()?,
+        };
+        let created = created.unwrap_or_else(|| {
+            if !date.is_empty() {
+                warn(&format!(
+                    "Failed to read the date {date}, dating the PDF when it is made"
+                ));
+            }
+            modified
+        });
+        Ok(Metadata {
+            title: options
+                .title
+                .clone()
+                .unwrap_or_else(|| format!("{name} CV")),
+            author: options.author.clone().unwrap_or_else(|| name.to_owned()),
+            subject: options
+                .subject
+                .clone()
+                .unwrap_or_else(|| DEFAULT_SUBJECT.to_owned()),
+            keywords: options.keywords.clone(),
+            creator: options
+                .creator
+                .clone()
+                .unwrap_or_else(|| APPLICATION.to_owned()),
+            created,
+            modified,
+            conformance,
+        })
+    }
+
+    /// Replaces the document information dictionary & adds the XMP metadata to the catalog.
+    pub(crate) fn add_to_document(&self, document: &mut Document) -> Result<()> {
+        let info = dictionary! {
+            "Title" => text_string(&self.title),
+            "Author" => text_string(&self.author),
+            "Subject" => text_string(&self.subject),
+            "Keywords" => text_string(&self.keywords.join(", ")),
+            "Creator" => text_string(&self.creator),
+            "Producer" => text_string(APPLICATION),
+            "CreationDate" => Object::string_literal(self.created.pdf_date()),
+            "ModDate" => Object::string_literal(self.modified.pdf_date()),
+            "Trapped" => "False",
+        };
+        if let Ok(info_id) = document.trailer.get(b"Info").and_then(Object::as_reference) {
+            let _previous = document.objects.insert(info_id, Object::Dictionary(info));
+        } else {
+            let info_id = document.add_object(info);
+            document.trailer.set("Info", info_id);
+        }
+        let mut metadata = Stream::new(
+            dictionary! {
+                "Type" => "Metadata",
+                "Subtype" => "XML",
+            },
+            self.xmp()?.into_bytes(),
+        );
+        // Left uncompressed, so the metadata can be read without parsing the PDF
+        metadata.allows_compression = false;
+        let metadata_id = document.add_object(metadata);
+        let catalog_id = document.trailer.get(b"Root")?.as_reference()?;
+        document
+            .get_object_mut(catalog_id)?
+            .as_dict_mut()?
+            .set("Metadata", metadata_id);
+        Ok(())
+    }
+
+    /// Writes the metadata as an XMP packet, matching the document information dictionary.
+    fn xmp(&self) -> Result<String> {
+        let mut keywords = String::new();
+        for keyword in &self.keywords {
+            write!(keywords, "<rdf:li>{}</rdf:li>", escape_xml(keyword))?;
+        }
+        Ok(format!(
+            "<?xpacket begin=\"\u{feff}\" id=\"W5M0MpCehiHzreSzNTczkc9d\"?>\n\
+             <x:xmpmeta xmlns:x=\"adobe:ns:meta/\">\n\
+             <rdf:RDF xmlns:rdf=\"http://www.w3.org/1999/02/22-rdf-syntax-ns#\">\n\
+             <rdf:Description rdf:about=\"\"\n \
+             xmlns:dc=\"http://purl.org/dc/elements/1.1/\"\n \
+             xmlns:xmp=\"http://ns.adobe.com/xap/1.0/\"\n \
+             xmlns:pdf=\"http://ns.adobe.com/pdf/1.3/\">\n\
+             <dc:format>application/pdf</dc:format>\n\
+             <dc:title><rdf:Alt><rdf:li xml:lang=\"x-default\">{title}</rdf:li></rdf:Alt></dc:title>\n\
+             <dc:creator><rdf:Seq><rdf:li>{author}</rdf:li></rdf:Seq></dc:creator>\n\
+             <dc:description><rdf:Alt><rdf:li xml:lang=\"x-default\">{subject}</rdf:li></rdf:Alt></dc:description>\n\
+             <dc:subject><rdf:Bag>{keywords}</rdf:Bag></dc:subject>\n\
+             <pdf:Keywords>{joined_keywords}</pdf:Keywords>\n\
+             <pdf:Producer>{producer}</pdf:Producer>\n\
+             <xmp:CreatorTool>{creator}</xmp:CreatorTool>\n\
+             <xmp:CreateDate>{created}</xmp:CreateDate>\n\
+             <xmp:ModifyDate>{modified}</xmp:ModifyDate>\n\
+             <xmp:MetadataDate>{modified}</xmp:MetadataDate>\n\
+             <pdf:Trapped>False</pdf:Trapped>\n\
+             </rdf:Description>\n\
+             {identification}\
+             </rdf:RDF>\n\
+             </x:xmpmeta>\n\
+             <?xpacket end=\"w\"?>",
+            title = escape_xml(&self.title),
+            author = escape_xml(&self.author),
+            subject = escape_xml(&self.subject),
+            joined_keywords = escape_xml(&self.keywords.join(", ")),
+            producer = escape_xml(APPLICATION),
+            creator = escape_xml(&self.creator),
+            created = self.created.xmp_date(),
+            modified = self.modified.xmp_date(),
+            identification = self.conformance.map(Conformance::xmp).unwrap_or_default(),
+        ))
+    }
+}
+
+/// Replaces the file identifier with a hash of the document, so every PDF is identified by its
+/// contents. printpdf writes the same identifier into every PDF.
+pub(crate) fn identify(document: &mut Document) -> Result<()> {
+    let _previous = document.trailer.remove(b"ID");
+    let mut pdf = Vec::new();
+    document.save_to(&mut pdf)?;
+    let hash = Sha256::digest(&pdf);
+    let identifier = hash.get(..IDENTIFIER_LENGTH).unwrap_or_default().to_vec();
+    // Both the permanent & the changing part, as the PDF is new
+    document.trailer.set(
+        "ID",
+        vec![
+            Object::String(identifier.clone(), StringFormat::Hexadecimal),
+            Object::String(identifier, StringFormat::Hexadecimal),
+        ],
+    );
+    Ok(())
+}
+
+/// Encodes text as a PDF text string, in UTF-16 unless it is plain ASCII.
+fn text_string(text: &str) -> Object {
+    if text.chars().all(|c| c.is_ascii() && !c.is_ascii_control()) {
+        return Object::string_literal(text);
+    }
+    // Starts with the byte order mark
+    let mut bytes = vec![0xfe_u8, 0xff_u8];
+    bytes.extend(text.encode_utf16().flat_map(u16::to_be_bytes));
+    Object::String(bytes, StringFormat::Hexadecimal)
+}
+
+/// Escapes the characters with a meaning in XML.
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unix_times_become_dates() {
+        assert!(Timestamp::from_unix(0).xmp_date() == "1970-01-01T00:00:00Z");
+        assert!(Timestamp::from_unix(951_782_400).xmp_date() == "2000-02-29T00:00:00Z");
+        assert!(Timestamp::from_unix(1_711_929_599).pdf_date() == "D:20240331235959Z");
+        assert!(Timestamp::from_unix(-1).xmp_date() == "1969-12-31T23:59:59Z");
+    }
+
+    #[test]
+    fn dates_are_read_in_any_format() {
+        for text in ["2024-04-01", "2024/4/1", "2024年4月1日"] {
+            assert!(
+                Timestamp::parse_date(text).map(|date| date.pdf_date())
+                    == Some("D:20240401000000Z".to_owned())
+            );
+        }
+    }
+
+    #[test]
+    fn invalid_dates_are_rejected() {
+        for text in [
+            "24-04-01",
+            "2024-13-01",
+            "2023-02-29",
+            "2024-04-31",
+            "April 2024",
+        ] {
+            assert!(Timestamp::parse_date(text).is_none());
+        }
+        assert!(Timestamp::parse_date("2024-02-29").is_some());
+    }
+}
@@ -0,0 +1,176 @@
+//! Detects & handles text that does not fit inside its text box.
+
+use super::font::{font_size_to_mm, FaceRequest, FontMap};
+use super::markup::{break_points, line_width, plain_text};
+use super::vertical::column_height;
+use crate::style::command::TextBox;
+use crate::style::core::{Direction, DEFAULT_LINE_HEIGHT};
+use anyhow::Result;
+use printpdf::Mm;
+
+/// How much the font size is reduced by on each attempt to fit the text.
+const SHRINK_STEP: f64 = 0.5_f64;
+/// The smallest font size text will be shrunk to.
+const MIN_FONT_SIZE: f64 = 4.0_f64;
+
+/// How far a block of text goes past the edges of its text box.
+pub(crate) struct Excess {
+    width: Mm,
+    height: Mm,
+}
+
+impl Excess {
+    pub(crate) fn fits(&self) -> bool {
+        self.width.0 <= 0.0_f64 && self.height.0 <= 0.0_f64
+    }
+
+    /// Describes the overflow for warnings & errors.
+    pub(crate) fn describe(&self, textbox: &TextBox) -> String {
+        let mut overflows = Vec::new();
+        if self.width.0 > 0.0_f64 {
+            overflows.push(format!("{:.1}mm horizontally", self.width.0));
+        }
+        if self.height.0 > 0.0_f64 {
+            overflows.push(format!("{:.1}mm vertically", self.height.0));
+        }
+        format!(
+            "{} overflows the text box on style line {} by {}",
+            textbox.value,
+            textbox.line_number,
+            overflows.join(" & ")
+        )
+    }
+}
+
+/// The length of a line of text along its direction, which is its height for vertical text.
+fn line_length(
+    line: &str,
+    vertical: bool,
+    request: FaceRequest<'_>,
+    fonts: &FontMap<'_>,
+    font_size: f64,
+) -> Result<Mm> {
+    if vertical {
+        Ok(column_height(
+            &plain_text(line),
+            font_size,
+            request.letter_spacing,
+        ))
+    } else {
+        line_width(line, request, fonts, font_size)
+    }
+}
+
+/// Wraps each line of the value so it fits across the text box, or down it for vertical text.
+/// Parts that cannot be wrapped any further are left on lines of their own, even if too long.
+pub(crate) fn wrap(
+    textbox: &TextBox,
+    value: &str,
+    request: FaceRequest<'_>,
+    fonts: &FontMap<'_>,
+    font_size: f64,
+) -> Result<String> {
+    let vertical = textbox.font_options.direction == Some(Direction::Vertical);
+    let limit = if vertical {
+        textbox.size.height
+    } else {
+        textbox.size.width
+    };
+    let mut wrapped = Vec::new();
+    for line in value.split('\n') {
+        let mut points = break_points(line);
+        points.push(line.len());
+        let mut start = 0;
+        // The furthest point the current line fits up to
+        let mut fitted = None;
+        let mut next = 0;
+        while let Some(point) = points.get(next).copied() {
+            let part = line.get(start..point).unwrap_or_default().trim_end();
+            if line_length(part, vertical, request, fonts, font_size)? <= limit {
+                fitted = Some(point);
+                next += 1;
+                continue;
+            }
+            // Wrap at the last point that fit, or here if nothing did
+            let end = fitted.take().unwrap_or_else(|| {
+                next += 1;
+                point
+            });
+            wrapped.push(line.get(start..end).unwrap_or_default().trim_end());
+            start = end;
+        }
+        if fitted.is_some() {
+            wrapped.push(line.get(start..).unwrap_or_default().trim_end());
+        }
+    }
+    Ok(wrapped.join("\n"))
+}
+
+/// Measures how far the text goes past the edges of the text box.
+pub(crate) fn measure_excess(
+    textbox: &TextBox,
+    value: &str,
+    request: FaceRequest<'_>,
+    fonts: &FontMap<'_>,
+    font_size: f64,
+) -> Result<Excess> {
+    let font_size_mm = font_size_to_mm(Some(font_size));
+    let vertical = textbox.font_options.direction == Some(Direction::Vertical);
+    let mut longest_line = Mm(0.0_f64);
+    let mut line_count = 0.0_f64;
+    for line in value.split('\n') {
+        let length = line_length(line, vertical, request, fonts, font_size)?;
+        if length > longest_line {
+            longest_line = length;
+        }
+        line_count += 1.0_f64;
+    }
+    // The last line only takes up the font size, however far apart the lines are
+    let line_height = textbox
+        .font_options
+        .line_height
+        .unwrap_or(DEFAULT_LINE_HEIGHT);
+    let block = font_size_mm * line_height.mul_add(line_count - 1.0_f64, 1.0_f64);
+    let (width, height) = if vertical {
+        (block, longest_line)
+    } else {
+        (longest_line, block)
+    };
+    Ok(Excess {
+        width: width - textbox.size.width,
+        height: height - textbox.size.height,
+    })
+}
+
+/// The value of a text box wrapped to fit at the largest font size, up to `font_size`.
+pub(crate) struct Fitted {
+    pub(crate) font_size: f64,
+    /// The value with line breaks added where it was wrapped.
+    pub(crate) value: String,
+    /// How far the text still overflows at the smallest allowed font size, if it does.
+    pub(crate) excess: Option<Excess>,
+}
+
+/// Finds the largest font size, up to `font_size`, that the text fits into the text box with,
+/// wrapping it again at every size. Stops at the smallest allowed font size if none fit.
+pub(crate) fn shrink_to_fit(
+    textbox: &TextBox,
+    value: &str,
+    request: FaceRequest<'_>,
+    fonts: &FontMap<'_>,
+    font_size: f64,
+) -> Result<Fitted> {
+    let mut font_size = font_size;
+    loop {
+        let wrapped = wrap(textbox, value, request, fonts, font_size)?;
+        let excess = measure_excess(textbox, &wrapped, request, fonts, font_size)?;
+        if excess.fits() || font_size - SHRINK_STEP < MIN_FONT_SIZE {
+            return Ok(Fitted {
+                font_size,
+                value: wrapped,
+                excess: (!excess.fits()).then_some(excess),
+            });
+        }
+        font_size -= SHRINK_STEP;
+    }
+}
@@ -0,0 +1,564 @@
+//! Makes the saved PDF conform to PDF/A, the archival subset of PDF: every font is embedded, the
+//! colours are tied to an sRGB output intent & the XMP metadata identifies the conformance level.
+//! Features the level forbids are reported as errors rather than silently dropped.
+
+use super::font::outline::remove_font_resources;
+use super::font::subset::{add_cid_sets, page_fonts};
+use super::resources::Resources;
+use anyhow::{anyhow, Result};
+use printpdf::lopdf::content::Content;
+use printpdf::lopdf::{dictionary, Dictionary, Document, Object, ObjectId, Stream};
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+
+/// The name of the colour space the output intent describes.
+const SRGB: &str = "sRGB IEC61966-2.1";
+/// The version 2 ICC profile of sRGB from Hewlett-Packard, as PDF/A-1 cannot use later versions.
+const SRGB_PROFILE: &[u8] = include_bytes!("../../assets/sRGB-IEC61966-2.1.icc");
+/// The text rendering mode that neither fills nor strokes the text.
+const INVISIBLE: i64 = 3;
+/// The annotation flag that prints the annotation along with the page.
+const PRINT_FLAG: i64 = 4;
+
+/// The PDF/A conformance levels that can be made.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Conformance {
+    /// PDF/A-1b, based on PDF 1.4, without transparency or optional content.
+    A1b,
+    /// PDF/A-2b, based on PDF 1.7.
+    A2b,
+}
+
+impl Conformance {
+    /// The part of ISO 19005 the level is defined in.
+    const fn part(self) -> u8 {
+        match self {
+            Conformance::A1b => 1,
+            Conformance::A2b => 2,
+        }
+    }
+
+    /// The PDF version the level is based on.
+    const fn version(self) -> &'static str {
+        match self {
+            Conformance::A1b => "1.4",
+            Conformance::A2b => "1.7",
+        }
+    }
+
+    /// The XMP description identifying the level, to add to the metadata.
+    pub(crate) fn xmp(self) -> String {
+        format!(
+            "<rdf:Description rdf:about=\"\"\n \
+             xmlns:pdfaid=\"http://www.aiim.org/pdfa/ns/id/\">\n\
+             <pdfaid:part>{}</pdfaid:part>\n\
+             <pdfaid:conformance>B</pdfaid:conformance>\n\
+             </rdf:Description>\n",
+            self.part()
+        )
+    }
+}
+
+impl Display for Conformance {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Conformance::A1b => write!(f, "1b"),
+            Conformance::A2b => write!(f, "2b"),
+        }
+    }
+}
+
+impl FromStr for Conformance {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "1b" => Ok(Conformance::A1b),
+            "2b" => Ok(Conformance::A2b),
+            _ => Err(anyhow!("Unknown PDF/A level {s}, expected 1b or 2b")),
+        }
+    }
+}
+
+/// The comment after the header of a PDF/A file, of bytes above 127 to mark it as binary.
+const BINARY_COMMENT: &[u8] = b"%\xE2\xE3\xCF\xD3\n";
+/// The length of an entry of the cross-reference table, such as `0000000015 00000 n \n`.
+const XREF_ENTRY_LENGTH: usize = 20;
+
+/// Adds the sRGB output intent that the device colours of the pages are interpreted in.
+fn add_output_intent(document: &mut Document, catalog_id: ObjectId) -> Result<()> {
+    let mut profile = Stream::new(dictionary! { "N" => 3_i64 }, SRGB_PROFILE.to_vec());
+    profile.compress()?;
+    let profile_id = document.add_object(profile);
+    let intent_id = document.add_object(dictionary! {
+        "Type" => "OutputIntent",
+        "S" => "GTS_PDFA1",
+        "OutputConditionIdentifier" => Object::string_literal(SRGB),
+        "RegistryName" => Object::string_literal("http://www.color.org"),
+        "Info" => Object::string_literal(SRGB),
+        "DestOutputProfile" => profile_id,
+    });
+    document
+        .get_object_mut(catalog_id)?
+        .as_dict_mut()?
+        .set("OutputIntents", vec![intent_id.into()]);
+    Ok(())
+}
+
+/// A decoded content stream, along with the fonts it draws with by name.
+struct ContentStream {
+    /// Whether the content is a page's, rather than an imported graphic's.
+    is_page: bool,
+    fonts: HashMap<Vec<u8>, ObjectId>,
+    content: Content,
+}
+
+/// The fonts in the resources of a form by name.
+fn form_fonts(document: &Document, form: &Dictionary) -> HashMap<Vec<u8>, ObjectId> {
+    form.get(b"Resources")
+        .and_then(|resources| document.dereference(resources))
+        .and_then(|(_, resources)| resources.as_dict())
+        .and_then(|resources| resources.get(b"Font"))
+        .and_then(|fonts| document.dereference(fonts))
+        .and_then(|(_, fonts)| fonts.as_dict())
+        .map(|fonts| {
+            fonts
+                .iter()
+                .filter_map(|(name, font)| Some((name.clone(), font.as_reference().ok()?)))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// The content streams of the pages & forms, decoded.
+fn contents(document: &Document) -> Result<Vec<ContentStream>> {
+    let mut contents = Vec::new();
+    for page_id in document.get_pages().into_values() {
+        contents.push(ContentStream {
+            is_page: true,
+            fonts: page_fonts(document, page_id),
+            content: document.get_and_decode_page_content(page_id)?,
+        });
+    }
+    for object in document.objects.values() {
+        let Object::Stream(stream) = object else {
+            continue;
+        };
+        if stream.dict.get(b"Subtype").and_then(Object::as_name).ok() != Some(b"Form") {
+            continue;
+        }
+        let data = if stream.dict.has(b"Filter") {
+            stream.decompressed_content()?
+        } else {
+            stream.content.clone()
+        };
+        contents.push(ContentStream {
+            is_page: false,
+            fonts: form_fonts(document, &stream.dict),
+            content: Content::decode(&data)?,
+        });
+    }
+    Ok(contents)
+}
+
+/// The fonts text is drawn with, along with those of them drawing text that can be seen,
+/// rather than only invisible text.
+fn drawn_fonts(contents: &[ContentStream]) -> (BTreeSet<ObjectId>, BTreeSet<ObjectId>) {
+    let mut drawn = BTreeSet::new();
+    let mut visible = BTreeSet::new();
+    for ContentStream { fonts, content, .. } in contents {
+        let mut rendering_mode = 0;
+        let mut saved = Vec::new();
+        let mut current_font = None;
+        for operation in &content.operations {
+            let operand = operation.operands.first();
+            match operation.operator.as_str() {
+                "q" => saved.push(rendering_mode),
+                "Q" => rendering_mode = saved.pop().unwrap_or_default(),
+                "Tr" => {
+                    rendering_mode = operand.and_then(|mode| mode.as_i64().ok()).unwrap_or(0);
+                }
+                "Tf" => {
+                    current_font = operand
+                        .and_then(|name| name.as_name().ok())
+                        .and_then(|name| fonts.get(name))
+                        .copied();
+                }
+                "Tj" | "'" | "\"" | "TJ" => {
+                    drawn.extend(current_font);
+                    if rendering_mode != INVISIBLE {
+                        visible.extend(current_font);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+    (drawn, visible)
+}
+
+/// Whether a font's glyphs are embedded in the PDF, or the font draws them itself.
+fn is_embedded(document: &Document, font: &Dictionary) -> bool {
+    let subtype = font
+        .get(b"Subtype")
+        .and_then(Object::as_name)
+        .unwrap_or_default();
+    if subtype == b"Type3" {
+        return true;
+    }
+    if subtype == b"Type0" {
+        return font
+            .get(b"DescendantFonts")
+            .and_then(|descendants| document.dereference(descendants))
+            .and_then(|(_, descendants)| descendants.as_array())
+            .ok()
+            .and_then(|descendants| descendants.first())
+            .and_then(|descendant| document.dereference(descendant).ok())
+            .and_then(|(_, descendant)| descendant.as_dict().ok())
+            .is_some_and(|descendant| is_embedded(document, descendant));
+    }
+    font.get(b"FontDescriptor")
+        .and_then(|descriptor| document.dereference(descriptor))
+        .and_then(|(_, descriptor)| descriptor.as_dict())
+        .is_ok_and(|descriptor| {
+            [b"FontFile".as_slice(), b"FontFile2", b"FontFile3"]
+                .into_iter()
+                .any(|key| descriptor.has(key))
+        })
+}
+
+/// Fails if a font drawing visible text is not embedded, such as one of the built-in fonts,
+/// & removes the fonts that are not embedded but never drawn.
+/// Fonts only drawing the invisible text layer of outlined text need not be embedded.
+fn check_fonts(
+    document: &mut Document,
+    contents: &[ContentStream],
+    conformance: Conformance,
+) -> Result<()> {
+    let (drawn, visible) = drawn_fonts(contents);
+    let page_fonts: BTreeSet<ObjectId> = contents
+        .iter()
+        .filter(|stream| stream.is_page)
+        .flat_map(|stream| stream.fonts.values().copied())
+        .collect();
+    let mut unused = BTreeSet::new();
+    for (id, object) in &document.objects {
+        let Ok(font) = object.as_dict() else {
+            continue;
+        };
+        if font.get(b"Type").and_then(Object::as_name).ok() != Some(b"Font")
+            || font
+                .get(b"Subtype")
+                .and_then(Object::as_name)
+                .is_ok_and(|subtype| subtype.starts_with(b"CIDFontType"))
+            || is_embedded(document, font)
+        {
+            continue;
+        }
+        if !visible.contains(id) {
+            if !drawn.contains(id) {
+                let _inserted = unused.insert(*id);
+            }
+            continue;
+        }
+        let name = font
+            .get(b"BaseFont")
+            .and_then(Object::as_name_str)
+            .unwrap_or("unnamed");
+        if page_fonts.contains(id) {
+            return Err(anyhow!(
+                "PDF/A-{conformance} needs every font embedded, but the font {name} is not; \
+                 use a TrueType or OpenType font instead of a built-in one"
+            ));
+        }
+        return Err(anyhow!(
+            "PDF/A-{conformance} needs every font embedded, but the font {name} of an imported \
+             graphic is not; embed it in the graphic"
+        ));
+    }
+    remove_font_resources(document, &unused);
+    Ok(())
+}
+
+/// Names the image or graphic an object was added from, for errors.
+fn describe(resources: &Resources, id: ObjectId) -> String {
+    resources
+        .label(id)
+        .unwrap_or_else(|| "an imported graphic".to_owned())
+}
+
+/// Fails on features that no PDF/A level allows, such as CMYK colours with the sRGB output intent.
+fn check_features(
+    document: &Document,
+    contents: &[ContentStream],
+    conformance: Conformance,
+    resources: &Resources,
+) -> Result<()> {
+    for (id, object) in &document.objects {
+        let dictionary = match object {
+            Object::Dictionary(dictionary) => dictionary,
+            Object::Stream(stream) => &stream.dict,
+            _ => continue,
+        };
+        let name = |key: &[u8]| dictionary.get(key).and_then(Object::as_name).ok();
+        let problem = if name(b"ColorSpace") == Some(b"DeviceCMYK") {
+            "uses CMYK colours, which the sRGB output intent cannot describe"
+        } else if name(b"Filter") == Some(b"LZWDecode") {
+            "is compressed with LZW"
+        } else if name(b"S") == Some(b"JavaScript") {
+            "runs JavaScript"
+        } else if name(b"Subtype") == Some(b"PS") {
+            "contains PostScript"
+        } else if dictionary.has(b"TR") {
+            "sets a transfer function"
+        } else {
+            continue;
+        };
+        return Err(anyhow!(
+            "PDF/A-{conformance} cannot contain {}, as it {problem}",
+            describe(resources, *id)
+        ));
+    }
+    for ContentStream {
+        is_page, content, ..
+    } in contents
+    {
+        let uses_cmyk = content
+            .operations
+            .iter()
+            .any(|operation| ["k", "K"].contains(&operation.operator.as_str()));
+        if uses_cmyk {
+            let source = if *is_page {
+                "a page"
+            } else {
+                "an imported graphic"
+            };
+            return Err(anyhow!(
+                "PDF/A-{conformance} cannot contain {source}, as it uses CMYK colours, \
+                 which the sRGB output intent cannot describe"
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Fails on transparency, which PDF/A-1 forbids, then removes the transparency groups that
+/// imported graphics declare even when nothing in them is transparent.
+fn remove_transparency(
+    document: &mut Document,
+    conformance: Conformance,
+    resources: &Resources,
+) -> Result<()> {
+    for (id, object) in &document.objects {
+        let dictionary = match object {
+            Object::Dictionary(dictionary) => dictionary,
+            Object::Stream(stream) => &stream.dict,
+            _ => continue,
+        };
+        let has_mask = dictionary
+            .get(b"SMask")
+            .is_ok_and(|mask| mask.as_name().ok() != Some(b"None"));
+        if has_mask {
+            return Err(anyhow!(
+                "PDF/A-{conformance} forbids transparency, but {} is transparent; \
+                 remove its opacity, flatten it or use --pdfa 2b",
+                describe(resources, *id)
+            ));
+        }
+        for key in [b"ca".as_slice(), b"CA"] {
+            if let Some(opacity) = dictionary
+                .get(key)
+                .ok()
+                .and_then(|value| value.as_float().ok())
+            {
+                if opacity < 1.0_f64 {
+                    return Err(anyhow!(
+                        "PDF/A-{conformance} forbids transparency, but an opacity of {opacity} \
+                         is used; remove the opacity from the style or use --pdfa 2b"
+                    ));
+                }
+            }
+        }
+        let blend_mode = dictionary.get(b"BM").and_then(Object::as_name).ok();
+        if blend_mode.is_some_and(|mode| mode != b"Normal" && mode != b"Compatible") {
+            return Err(anyhow!(
+                "PDF/A-{conformance} forbids transparency, but {} uses a blend mode; \
+                 use --pdfa 2b",
+                describe(resources, *id)
+            ));
+        }
+    }
+    for object in document.objects.values_mut() {
+        let dictionary = match object {
+            Object::Dictionary(dictionary) => dictionary,
+            Object::Stream(stream) => &mut stream.dict,
+            _ => continue,
+        };
+        let is_transparency = dictionary
+            .get(b"Group")
+            .and_then(Object::as_dict)
+            .and_then(|group| group.get(b"S"))
+            .and_then(Object::as_name)
+            .is_ok_and(|kind| kind == b"Transparency");
+        if is_transparency {
+            let _group = dictionary.remove(b"Group");
+        }
+    }
+    Ok(())
+}
+
+/// Makes the optional content of the layers conform: PDF/A-1 has none, so it is removed,
+/// while PDF/A-2 needs every configuration to be named.
+fn conform_optional_content(
+    document: &mut Document,
+    catalog_id: ObjectId,
+    conformance: Conformance,
+) -> Result<()> {
+    let catalog = document.get_object_mut(catalog_id)?.as_dict_mut()?;
+    if conformance == Conformance::A1b {
+        let _properties = catalog.remove(b"OCProperties");
+        return Ok(());
+    }
+    let Ok(Object::Dictionary(properties)) = catalog.get_mut(b"OCProperties") else {
+        return Ok(());
+    };
+    if let Ok(Object::Dictionary(default)) = properties.get_mut(b"D") {
+        if !default.has(b"Name") {
+            default.set("Name", Object::string_literal("Layers"));
+        }
+    }
+    if let Ok(Object::Array(configurations)) = properties.get_mut(b"Configs") {
+        for (index, configuration) in configurations.iter_mut().enumerate() {
+            if let Object::Dictionary(configuration) = configuration {
+                if !configuration.has(b"Name") {
+                    configuration.set(
+                        "Name",
+                        Object::string_literal(format!("Layers {}", index + 2)),
+                    );
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Marks every annotation to be printed, as PDF/A requires.
+fn print_annotations(document: &mut Document) {
+    for object in document.objects.values_mut() {
+        let Object::Dictionary(dictionary) = object else {
+            continue;
+        };
+        if dictionary.get(b"Type").and_then(Object::as_name).ok() != Some(b"Annot") {
+            continue;
+        }
+        let flags = dictionary
+            .get(b"F")
+            .and_then(Object::as_i64)
+            .unwrap_or_default();
+        dictionary.set("F", flags | PRINT_FLAG);
+    }
+}
+
+/// Checks that the document can conform to the PDF/A level & makes it conform.
+/// The XMP identification is written along with the rest of the metadata.
+pub(crate) fn make_conformant(
+    document: &mut Document,
+    conformance: Conformance,
+    resources: &Resources,
+) -> Result<()> {
+    let contents = contents(document)?;
+    check_fonts(document, &contents, conformance)?;
+    check_features(document, &contents, conformance, resources)?;
+    let catalog_id = document.trailer.get(b"Root")?.as_reference()?;
+    if conformance == Conformance::A1b {
+        remove_transparency(document, conformance, resources)?;
+        add_cid_sets(document)?;
+    }
+    conform_optional_content(document, catalog_id, conformance)?;
+    print_annotations(document);
+    add_output_intent(document, catalog_id)?;
+    conformance.version().clone_into(&mut document.version);
+    let _pruned = document.prune_objects();
+    Ok(())
+}
+
+/// Adds a comment of bytes above 127 after the header of a saved PDF, marking the file as binary
+/// as PDF/A requires, & moves the offsets of the cross-reference table along with the objects.
+pub(crate) fn mark_binary(pdf: &mut Vec<u8>) -> Result<()> {
+    let malformed = || anyhow!("Failed to mark the PDF as binary");
+    let header_end = pdf
+        .iter()
+        .position(|byte| *byte == b'\n')
+        .ok_or_else(malformed)?
+        + 1;
+    drop(pdf.splice(header_end..header_end, BINARY_COMMENT.iter().copied()));
+    let shift = u64::try_from(BINARY_COMMENT.len())?;
+    let (start, end) = last_number_after(pdf, b"startxref\n").ok_or_else(malformed)?;
+    let xref_start = read_number(pdf, start, end).ok_or_else(malformed)? + shift;
+    drop(pdf.splice(start..end, xref_start.to_string().into_bytes()));
+    let xref_start = usize::try_from(xref_start)?;
+    let table = pdf.get(xref_start..).ok_or_else(malformed)?;
+    let mut lines = table.split(|byte| *byte == b'\n');
+    let (Some(b"xref"), Some(section)) = (lines.next(), lines.next()) else {
+        return Err(malformed());
+    };
+    let count = section
+        .split(|byte| *byte == b' ')
+        .nth(1)
+        .and_then(|count| std::str::from_utf8(count).ok()?.parse::<usize>().ok())
+        .ok_or_else(malformed)?;
+    let mut entry_start = xref_start + "xref\n".len() + section.len() + 1;
+    for _ in 0..count {
+        let entry = pdf
+            .get_mut(entry_start..entry_start + XREF_ENTRY_LENGTH)
+            .ok_or_else(malformed)?;
+        // Entries are a 10 digit offset, a 5 digit generation & `n` for objects in use
+        if entry.get(17) == Some(&b'n') {
+            let offset = read_number(entry, 0, 10).ok_or_else(malformed)? + shift;
+            entry
+                .get_mut(..10)
+                .ok_or_else(malformed)?
+                .copy_from_slice(format!("{offset:010}").as_bytes());
+        }
+        entry_start += XREF_ENTRY_LENGTH;
+    }
+    Ok(())
+}
+
+/// Finds the digits after the last occurrence of `key`, returning where they start & end.
+fn last_number_after(pdf: &[u8], key: &[u8]) -> Option<(usize, usize)> {
+    let start = pdf.windows(key.len()).rposition(|window| window == key)? + key.len();
+    let length = pdf
+        .get(start..)?
+        .iter()
+        .take_while(|byte| byte.is_ascii_digit())
+        .count();
+    Some((start, start + length))
+}
+
+/// Reads the number written in a range of bytes.
+fn read_number(bytes: &[u8], start: usize, end: usize) -> Option<u64> {
+    std::str::from_utf8(bytes.get(start..end)?)
+        .ok()?
+        .parse()
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn binary_files_keep_their_objects() -> Result<()> {
+        let mut document = Document::with_version("1.4");
+        let id = document.add_object(Object::string_literal("text"));
+        let mut pdf = Vec::new();
+        document.save_to(&mut pdf)?;
+        mark_binary(&mut pdf)?;
+        assert!(pdf.starts_with(b"%PDF-1.4\n%\xE2\xE3\xCF\xD3\n"));
+        let loaded = Document::load_mem(&pdf)?;
+        assert!(loaded.get_object(id)?.as_str()? == b"text");
+        Ok(())
+    }
+}
@@ -0,0 +1,62 @@
+//! Encodes values as QR codes & draws them as filled rectangles, so they stay sharp when printed.
+
+use crate::style::command::QrCode as QrCodeCommand;
+use crate::style::core::{ErrorCorrection, Point, DEFAULT_QUIET_ZONE};
+use anyhow::{anyhow, Result};
+use printpdf::lopdf::content::Operation;
+use printpdf::lopdf::Object;
+use printpdf::{Color, Greyscale, PdfLayerReference, Pt};
+use qrcode::{EcLevel, QrCode};
+
+fn ec_level(error_correction: ErrorCorrection) -> EcLevel {
+    match error_correction {
+        ErrorCorrection::Low => EcLevel::L,
+        ErrorCorrection::Medium => EcLevel::M,
+        ErrorCorrection::Quartile => EcLevel::Q,
+        ErrorCorrection::High => EcLevel::H,
+    }
+}
+
+/// Draws a QR code encoding `value` with its top left corner at `top_left`.
+/// Each run of dark modules in a row is drawn as a single rectangle.
+pub(crate) fn draw_qr_code(
+    qr_code: &QrCodeCommand,
+    value: &str,
+    top_left: Point,
+    layer: &PdfLayerReference,
+) -> Result<()> {
+    let level = ec_level(qr_code.error_correction.unwrap_or_default());
+    let code = QrCode::with_error_correction_level(value, level)
+        .map_err(|error| anyhow!("Failed to encode {value} as a QR code: {error}"))?;
+    let width = code.width();
+    let quiet_zone = f64::from(qr_code.quiet_zone.unwrap_or(DEFAULT_QUIET_ZONE));
+    let modules = quiet_zone.mul_add(2.0_f64, f64::from(u32::try_from(width)?));
+    let module_size = Pt::from(qr_code.size).0 / modules;
+    let (left, top) = (Pt::from(top_left.x).0, Pt::from(top_left.y).0);
+    layer.save_graphics_state();
+    layer.set_fill_color(Color::Greyscale(Greyscale::new(0.0_f64, None)));
+    for (row, colors) in code.to_colors().chunks(width).enumerate() {
+        let y = (quiet_zone + f64::from(u32::try_from(row + 1)?)).mul_add(-module_size, top);
+        let mut column = 0;
+        while column < width {
+            if colors.get(column) != Some(&qrcode::Color::Dark) {
+                column += 1;
+                continue;
+            }
+            let start = column;
+            while colors.get(column) == Some(&qrcode::Color::Dark) {
+                column += 1;
+            }
+            let x = (quiet_zone + f64::from(u32::try_from(start)?)).mul_add(module_size, left);
+            let run = f64::from(u32::try_from(column - start)?) * module_size;
+            let rectangle = [x, y, run, module_size];
+            layer.add_operation(Operation::new(
+                "re",
+                rectangle.into_iter().map(Object::Real).collect(),
+            ));
+        }
+    }
+    layer.add_operation(Operation::new("f", Vec::new()));
+    layer.restore_graphics_state();
+    Ok(())
+}
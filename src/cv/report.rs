@@ -0,0 +1,81 @@
+//! Reports warnings, along with which assets take up the most space when the PDF is bigger
+//! than wanted.
+
+use super::resources::Resources;
+use printpdf::lopdf::{Document, Object, ObjectId};
+use std::collections::HashMap;
+
+/// The number of assets listed in the report.
+const REPORTED_ASSETS: usize = 5;
+const KIBIBYTE: u64 = 1024;
+const MEBIBYTE: u64 = 1024 * 1024;
+
+/// Formats a number of bytes with one decimal, in the largest fitting unit.
+fn format_size(bytes: u64) -> String {
+    let (unit, name) = if bytes >= MEBIBYTE {
+        (MEBIBYTE, "MB")
+    } else {
+        (KIBIBYTE, "KB")
+    };
+    let tenths = (bytes * 10).div_euclid(unit);
+    format!("{}.{} {name}", tenths.div_euclid(10), tenths.rem_euclid(10))
+}
+
+/// Names the fonts embedded in the document by the IDs of their font files.
+#[allow(unused_results, reason = "a font file is only used by one font")]
+fn font_labels(document: &Document) -> HashMap<ObjectId, String> {
+    let mut labels = HashMap::new();
+    for object in document.objects.values() {
+        let Ok(dictionary) = object.as_dict() else {
+            continue;
+        };
+        if dictionary.get(b"Type").and_then(Object::as_name).ok() != Some(b"FontDescriptor") {
+            continue;
+        }
+        let name = dictionary
+            .get(b"FontName")
+            .and_then(Object::as_name_str)
+            .unwrap_or("unknown");
+        for key in [b"FontFile".as_slice(), b"FontFile2", b"FontFile3"] {
+            if let Ok(id) = dictionary.get(key).and_then(Object::as_reference) {
+                labels.insert(id, format!("font {name}"));
+            }
+        }
+    }
+    labels
+}
+
+/// Reports something that did not stop the CV from being made, but may not look as intended.
+pub(crate) fn warn(message: &str) {
+    eprintln!("Warning: {message}");
+}
+
+/// Warns with the largest assets in the document if the saved PDF is over `max_size` bytes.
+pub(crate) fn report_size(document: &Document, size: u64, max_size: u64, resources: &Resources) {
+    if size <= max_size {
+        return;
+    }
+    let fonts = font_labels(document);
+    let mut assets: HashMap<String, u64> = HashMap::new();
+    for (id, object) in &document.objects {
+        let Object::Stream(stream) = object else {
+            continue;
+        };
+        let label = resources
+            .label(*id)
+            .or_else(|| fonts.get(id).cloned())
+            .unwrap_or_else(|| "page contents & other data".to_owned());
+        *assets.entry(label).or_default() +=
+            u64::try_from(stream.content.len()).unwrap_or(u64::MAX);
+    }
+    let mut assets: Vec<(String, u64)> = assets.into_iter().collect();
+    assets.sort_by(|first, second| second.1.cmp(&first.1).then(first.0.cmp(&second.0)));
+    warn(&format!(
+        "The PDF is {}, over the maximum of {}. The largest assets are:",
+        format_size(size),
+        format_size(max_size)
+    ));
+    for (label, bytes) in assets.iter().take(REPORTED_ASSETS) {
+        eprintln!("  {:>9}  {label}", format_size(*bytes));
+    }
+}
@@ -0,0 +1,361 @@
+//! Images, forms & graphics states that printpdf cannot create, added to the PDF once it is saved.
+//! They are drawn by name from the page contents & registered on the pages that draw them.
+//! Identical resources are only added once, however often they are drawn.
+
+use super::vector::{import_form, VectorImage};
+use crate::style::core::Size;
+use anyhow::{anyhow, Result};
+use printpdf::image_crate::codecs::jpeg::JpegEncoder;
+use printpdf::image_crate::imageops::FilterType;
+use printpdf::image_crate::{ColorType, DynamicImage};
+use printpdf::lopdf::{dictionary, Dictionary, Document, Object, ObjectId, Stream};
+use printpdf::Mm;
+use sha2::{Digest, Sha256};
+use std::borrow::Cow;
+use std::cell::{Cell, RefCell};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+/// The millimetres in an inch, to turn placed sizes into resolutions.
+const MM_PER_INCH: f64 = 25.4_f64;
+
+/// An image with 8 bits per channel, along with its transparency.
+struct EmbeddedImage {
+    width: u32,
+    height: u32,
+    /// Whether the pixels are a single gray channel rather than RGB.
+    gray: bool,
+    /// The raw pixels, or the whole file when encoded as JPEG.
+    data: Vec<u8>,
+    /// Whether the data is a JPEG file, rather than raw pixels.
+    jpeg: bool,
+    /// The opacity of each pixel, if the image has any transparency.
+    alpha: Option<Vec<u8>>,
+    /// Where the image came from, to report its size.
+    label: String,
+}
+
+/// The resources added to the document so far.
+pub(crate) struct Resources {
+    /// The resolution to downsample images to for the size they are drawn at.
+    dpi: Option<f64>,
+    /// The quality to re-encode images as JPEG with, from 1 to 100.
+    jpeg_quality: Option<u8>,
+    /// The index of the page being drawn.
+    page: Cell<usize>,
+    images: RefCell<Vec<EmbeddedImage>>,
+    forms: RefCell<Vec<VectorImage>>,
+    opacities: RefCell<Vec<f64>>,
+    /// The name each resource was added with, by the hash of its contents.
+    names: RefCell<HashMap<[u8; 32], String>>,
+    /// The names of the resources drawn on each page, by the index of the page.
+    used: RefCell<BTreeMap<usize, BTreeSet<String>>>,
+    /// Where each object added to the document came from.
+    labels: RefCell<HashMap<ObjectId, String>>,
+}
+
+/// Hashes the parts that make up a resource, along with its kind.
+fn content_hash(kind: &str, parts: &[&[u8]]) -> [u8; 32] {
+    let mut hasher = Sha256::new().chain_update(kind);
+    for part in parts {
+        hasher.update(part.len().to_le_bytes());
+        hasher.update(part);
+    }
+    hasher.finalize().into()
+}
+
+/// The number of pixels needed to draw `length` at `dpi`.
+#[allow(
+    clippy::as_conversions,
+    clippy::cast_possible_truncation,
+    clippy::cast_sign_loss,
+    reason = "the number of pixels is positive & no larger than the image"
+)]
+fn pixels_for(length: Mm, dpi: f64, limit: u32) -> u32 {
+    let pixels = (length.0 / MM_PER_INCH * dpi).ceil().max(1.0_f64);
+    if pixels >= f64::from(limit) {
+        limit
+    } else {
+        pixels as u32
+    }
+}
+
+impl Resources {
+    pub(crate) fn new(dpi: Option<f64>, jpeg_quality: Option<u8>) -> Self {
+        Resources {
+            dpi,
+            jpeg_quality,
+            page: Cell::new(0),
+            images: RefCell::new(Vec::new()),
+            forms: RefCell::new(Vec::new()),
+            opacities: RefCell::new(Vec::new()),
+            names: RefCell::new(HashMap::new()),
+            used: RefCell::new(BTreeMap::new()),
+            labels: RefCell::new(HashMap::new()),
+        }
+    }
+
+    /// Moves on to drawing the next page.
+    pub(crate) fn next_page(&self) {
+        self.page.set(self.page.get() + 1);
+    }
+
+    /// Names a resource, reusing the name of an identical resource added before,
+    /// & records that it is drawn on the current page.
+    #[allow(unused_results, reason = "every hash & name is only added once")]
+    fn name(&self, hash: [u8; 32], add: impl FnOnce() -> String) -> String {
+        let name = self
+            .names
+            .borrow_mut()
+            .entry(hash)
+            .or_insert_with(add)
+            .clone();
+        self.used
+            .borrow_mut()
+            .entry(self.page.get())
+            .or_default()
+            .insert(name.clone());
+        name
+    }
+
+    /// Scales an image down to the resolution wanted for the size it is drawn at.
+    fn downsample<'a>(&self, image: &'a DynamicImage, size: Size) -> Cow<'a, DynamicImage> {
+        let Some(dpi) = self.dpi else {
+            return Cow::Borrowed(image);
+        };
+        let width = pixels_for(size.width, dpi, image.width());
+        let height = pixels_for(size.height, dpi, image.height());
+        if width == image.width() && height == image.height() {
+            return Cow::Borrowed(image);
+        }
+        Cow::Owned(image.resize_exact(width, height, FilterType::CatmullRom))
+    }
+
+    /// Adds an image drawn at `size`, returning the name to draw it with.
+    /// The JPEG file the image was decoded from is embedded as is, when given & the image is not
+    /// scaled down or re-encoded.
+    pub(crate) fn add_image(
+        &self,
+        image: &DynamicImage,
+        jpeg: Option<Vec<u8>>,
+        size: Size,
+        label: &str,
+    ) -> Result<String> {
+        let image = self.downsample(image, size);
+        if let (Some(data), Cow::Borrowed(_), None) = (jpeg, &image, self.jpeg_quality) {
+            let embedded = EmbeddedImage {
+                width: image.width(),
+                height: image.height(),
+                gray: !image.color().has_color(),
+                data,
+                jpeg: true,
+                alpha: None,
+                label: label.to_owned(),
+            };
+            return Ok(self.add_embedded_image(embedded));
+        }
+        let color = image.color();
+        let alpha = color.has_alpha().then(|| {
+            image
+                .to_rgba8()
+                .pixels()
+                .map(|pixel| pixel.0[3])
+                .collect::<Vec<u8>>()
+        });
+        // Fully opaque images do not need a mask
+        let alpha = alpha.filter(|values| values.iter().any(|value| *value < u8::MAX));
+        let gray = !color.has_color();
+        let (pixels, color_type) = if gray {
+            (image.to_luma8().into_raw(), ColorType::L8)
+        } else {
+            (image.to_rgb8().into_raw(), ColorType::Rgb8)
+        };
+        let data = if let Some(quality) = self.jpeg_quality {
+            let mut encoded = Vec::new();
+            JpegEncoder::new_with_quality(&mut encoded, quality).encode(
+                &pixels,
+                image.width(),
+                image.height(),
+                color_type,
+            )?;
+            encoded
+        } else {
+            pixels
+        };
+        Ok(self.add_embedded_image(EmbeddedImage {
+            width: image.width(),
+            height: image.height(),
+            gray,
+            data,
+            jpeg: self.jpeg_quality.is_some(),
+            alpha,
+            label: label.to_owned(),
+        }))
+    }
+
+    /// Adds an image unless an identical one was added before, returning the name to draw it with.
+    fn add_embedded_image(&self, image: EmbeddedImage) -> String {
+        let hash = content_hash(
+            "image",
+            &[
+                &image.width.to_le_bytes(),
+                &image.height.to_le_bytes(),
+                &[u8::from(image.gray), u8::from(image.jpeg)],
+                &image.data,
+                image.alpha.as_deref().unwrap_or_default(),
+            ],
+        );
+        self.name(hash, || {
+            let mut images = self.images.borrow_mut();
+            images.push(image);
+            format!("CvImage{}", images.len())
+        })
+    }
+
+    /// Adds a vector image as a form, returning the name to draw it with.
+    pub(crate) fn add_form(&self, vector: VectorImage) -> String {
+        self.name(content_hash("form", &[&vector.digest()]), || {
+            let mut forms = self.forms.borrow_mut();
+            forms.push(vector);
+            format!("CvForm{}", forms.len())
+        })
+    }
+
+    /// Adds a graphics state that draws with the given opacity, returning the name to set it with.
+    pub(crate) fn add_opacity(&self, opacity: f64) -> String {
+        let hash = content_hash("opacity", &[&opacity.to_bits().to_le_bytes()]);
+        self.name(hash, || {
+            let mut opacities = self.opacities.borrow_mut();
+            opacities.push(opacity);
+            format!("CvOpacity{}", opacities.len())
+        })
+    }
+
+    /// Where an object added to the document came from, if it was added from a file.
+    pub(crate) fn label(&self, id: ObjectId) -> Option<String> {
+        self.labels.borrow().get(&id).cloned()
+    }
+
+    /// Adds the collected resources to the pages of a saved PDF that draw them.
+    #[allow(unused_results, reason = "every object is only labelled & named once")]
+    pub(crate) fn add_to_document(&self, document: &mut Document) -> Result<()> {
+        // The category of page resources & the object of each name
+        let mut objects: HashMap<String, (&[u8], ObjectId)> = HashMap::new();
+        let mut labels = self.labels.borrow_mut();
+        for (index, image) in self.images.borrow().iter().enumerate() {
+            let (id, mask_id) = add_image_stream(document, image)?;
+            objects.insert(format!("CvImage{}", index + 1), (b"XObject", id));
+            labels.insert(id, image.label.clone());
+            if let Some(mask_id) = mask_id {
+                labels.insert(mask_id, format!("{} (transparency)", image.label));
+            }
+        }
+        for (index, vector) in self.forms.borrow().iter().enumerate() {
+            let id = import_form(document, vector)?;
+            objects.insert(format!("CvForm{}", index + 1), (b"XObject", id));
+            labels.insert(id, vector.label().to_owned());
+        }
+        for (index, opacity) in self.opacities.borrow().iter().enumerate() {
+            let id = document.add_object(dictionary! {
+                "Type" => "ExtGState",
+                "ca" => *opacity,
+                "CA" => *opacity,
+            });
+            objects.insert(format!("CvOpacity{}", index + 1), (b"ExtGState", id));
+        }
+        let pages = document.get_pages();
+        for (page, names) in self.used.borrow().iter() {
+            let page_number = u32::try_from(page + 1)?;
+            let page_id = *pages
+                .get(&page_number)
+                .ok_or_else(|| anyhow!("Failed to find page {page_number} to add resources to"))?;
+            let resources_id = indirect_dictionary(document, page_id, b"Resources")?;
+            for name in names {
+                let (category, id) = *objects
+                    .get(name)
+                    .ok_or_else(|| anyhow!("Failed to find the resource {name}"))?;
+                let category_id = indirect_dictionary(document, resources_id, category)?;
+                document
+                    .get_object_mut(category_id)?
+                    .as_dict_mut()?
+                    .set(name.clone(), id);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Adds an image & its transparency mask as compressed streams,
+/// returning the IDs of the image & the mask.
+fn add_image_stream(
+    document: &mut Document,
+    image: &EmbeddedImage,
+) -> Result<(ObjectId, Option<ObjectId>)> {
+    let color_space = if image.gray {
+        "DeviceGray"
+    } else {
+        "DeviceRGB"
+    };
+    let mut dictionary = dictionary! {
+        "Type" => "XObject",
+        "Subtype" => "Image",
+        "Width" => image.width,
+        "Height" => image.height,
+        "ColorSpace" => color_space,
+        "BitsPerComponent" => 8_i64,
+    };
+    let mut mask_id = None;
+    if let Some(alpha) = &image.alpha {
+        let mut mask = Stream::new(
+            dictionary! {
+                "Type" => "XObject",
+                "Subtype" => "Image",
+                "Width" => image.width,
+                "Height" => image.height,
+                "ColorSpace" => "DeviceGray",
+                "BitsPerComponent" => 8_i64,
+            },
+            alpha.clone(),
+        );
+        mask.compress()?;
+        let id = document.add_object(mask);
+        dictionary.set("SMask", id);
+        mask_id = Some(id);
+    }
+    let stream = if image.jpeg {
+        dictionary.set("Filter", "DCTDecode");
+        Stream::new(dictionary, image.data.clone()).with_compression(false)
+    } else {
+        let mut stream = Stream::new(dictionary, image.data.clone());
+        stream.compress()?;
+        stream
+    };
+    Ok((document.add_object(stream), mask_id))
+}
+
+/// Makes sure the entry of a dictionary object is a dictionary stored as its own object,
+/// returning the ID of that object.
+fn indirect_dictionary(
+    document: &mut Document,
+    owner_id: ObjectId,
+    key: &[u8],
+) -> Result<ObjectId> {
+    let existing = document
+        .get_object(owner_id)?
+        .as_dict()?
+        .get(key)
+        .ok()
+        .cloned();
+    if let Some(Object::Reference(id)) = existing {
+        return Ok(id);
+    }
+    let dictionary = match existing {
+        Some(Object::Dictionary(dictionary)) => dictionary,
+        _ => Dictionary::new(),
+    };
+    let id = document.add_object(dictionary);
+    document
+        .get_object_mut(owner_id)?
+        .as_dict_mut()?
+        .set(key.to_vec(), id);
+    Ok(id)
+}
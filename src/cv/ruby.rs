@@ -0,0 +1,289 @@
+//! Sets furigana (ruby) above base text, either from inline `{base|ruby}` markup or the `ruby` command.
+
+use super::font::Typeface;
+use anyhow::Result;
+use printpdf::{Mm, PdfLayerReference};
+
+/// The size of ruby text relative to the base text.
+const RUBY_SCALE: f64 = 0.5_f64;
+
+/// A run of base text, along with the ruby to set above it.
+pub(crate) struct Segment<'a> {
+    pub(crate) base: &'a str,
+    pub(crate) ruby: Option<&'a str>,
+}
+
+/// Splits a line into segments, turning `{base|ruby}` into annotated segments.
+/// Braces that do not form valid markup are kept as plain text.
+pub(crate) fn parse_markup(line: &str) -> Vec<Segment<'_>> {
+    let mut segments = Vec::new();
+    let mut rest = line;
+    while let Some(start) = rest.find('{') {
+        let markup = rest.get(start + 1..).unwrap_or_default();
+        let Some((base, ruby)) = markup
+            .find('}')
+            .and_then(|end| markup.get(..end))
+            .and_then(|inner| inner.split_once('|'))
+        else {
+            segments.push(Segment {
+                base: rest.get(..=start).unwrap_or_default(),
+                ruby: None,
+            });
+            rest = markup;
+            continue;
+        };
+        if start > 0 {
+            segments.push(Segment {
+                base: rest.get(..start).unwrap_or_default(),
+                ruby: None,
+            });
+        }
+        segments.push(Segment {
+            base,
+            ruby: Some(ruby),
+        });
+        rest = markup
+            .get(base.len() + ruby.len() + 2..)
+            .unwrap_or_default();
+    }
+    if !rest.is_empty() {
+        segments.push(Segment {
+            base: rest,
+            ruby: None,
+        });
+    }
+    segments
+}
+
+/// Removes any ruby markup from a line, leaving only the base text.
+pub(crate) fn strip_markup(line: &str) -> String {
+    parse_markup(line)
+        .iter()
+        .map(|segment| segment.base)
+        .collect()
+}
+
+/// Pairs each space separated group of the base text with the matching group of the ruby,
+/// so each part of a name gets its own reading.
+/// Falls back to setting the whole ruby over the whole base when the groups do not line up.
+pub(crate) fn pair_groups<'a>(base: &'a str, ruby: &'a str) -> Vec<Segment<'a>> {
+    let is_space = |c: char| c == ' ' || c == '\u{3000}';
+    let base_groups: Vec<&str> = base.split(is_space).collect();
+    let ruby_groups: Vec<&str> = ruby
+        .split(is_space)
+        .filter(|group| !group.is_empty())
+        .collect();
+    let non_empty_base = base_groups.iter().filter(|group| !group.is_empty()).count();
+    if non_empty_base != ruby_groups.len() {
+        return vec![Segment {
+            base,
+            ruby: Some(ruby),
+        }];
+    }
+    let mut readings = ruby_groups.into_iter();
+    let mut segments = Vec::new();
+    let mut offset = 0;
+    for (index, group) in base_groups.into_iter().enumerate() {
+        if index > 0 {
+            // Keep the separator between groups
+            let separator_length = base
+                .get(offset..)
+                .and_then(|rest| rest.chars().next())
+                .map_or(0, char::len_utf8);
+            segments.push(Segment {
+                base: base
+                    .get(offset..offset + separator_length)
+                    .unwrap_or_default(),
+                ruby: None,
+            });
+            offset += separator_length;
+        }
+        let reading = if group.is_empty() {
+            None
+        } else {
+            readings.next()
+        };
+        segments.push(Segment {
+            base: group,
+            ruby: reading,
+        });
+        offset += group.len();
+    }
+    segments
+}
+
+/// Where the base text & ruby of a segment go, relative to the start of the segment.
+struct Layout {
+    /// The width the segment takes up in the line.
+    width: Mm,
+    /// Where the base text starts.
+    base_x: Mm,
+    /// The extra space after each character of the base text.
+    base_spacing: Mm,
+    /// Where the ruby starts.
+    ruby_x: Mm,
+}
+
+/// Lays out a segment whose base text has `characters` characters.
+/// A ruby narrower than its base is centered above it. A wider one widens the segment instead,
+/// with the base characters spread out under it, so it never overhangs the neighbouring text.
+fn layout(base_width: Mm, characters: u32, ruby_width: Mm) -> Layout {
+    if ruby_width <= base_width {
+        return Layout {
+            width: base_width,
+            base_x: Mm(0.0_f64),
+            base_spacing: Mm(0.0_f64),
+            ruby_x: (base_width - ruby_width) / 2.0_f64,
+        };
+    }
+    let excess = ruby_width - base_width;
+    let base_spacing = excess / f64::from(characters.max(1));
+    Layout {
+        width: ruby_width,
+        // Half of the spacing goes before the first character & half after the last
+        base_x: base_spacing / 2.0_f64,
+        base_spacing,
+        ruby_x: Mm(0.0_f64),
+    }
+}
+
+/// Lays out a segment as it is drawn in `typeface`.
+fn layout_segment(
+    segment: &Segment<'_>,
+    typeface: &Typeface<'_>,
+    font_size: f64,
+) -> Result<Layout> {
+    let base_width = typeface.text_width(segment.base, font_size)?;
+    let ruby_width = match segment.ruby {
+        Some(ruby) => typeface.text_width(ruby, font_size * RUBY_SCALE)?,
+        None => Mm(0.0_f64),
+    };
+    let characters = u32::try_from(segment.base.chars().count())?;
+    Ok(layout(base_width, characters, ruby_width))
+}
+
+/// Measures the width a segment takes up in the line, which is that of its ruby when wider.
+pub(crate) fn segment_width(
+    segment: &Segment<'_>,
+    typeface: &Typeface<'_>,
+    font_size: f64,
+) -> Result<Mm> {
+    Ok(layout_segment(segment, typeface, font_size)?.width)
+}
+
+/// Draws a line of segments starting at `x`, with ruby above each annotated segment.
+/// Returns the width of the drawn line.
+pub(crate) fn draw_segments(
+    segments: &[Segment<'_>],
+    x: Mm,
+    baseline: Mm,
+    typeface: &Typeface<'_>,
+    font_size: f64,
+    layer: &PdfLayerReference,
+) -> Result<Mm> {
+    let font = typeface.font;
+    let ruby_size = font_size * RUBY_SCALE;
+    // Rest the ruby's descenders on top of the base text
+    let ruby_baseline = baseline + font.ascent(font_size) - font.descent(ruby_size);
+    let mut width = Mm(0.0_f64);
+    for segment in segments {
+        let layout = layout_segment(segment, typeface, font_size)?;
+        let base_x = x + width + layout.base_x;
+        if layout.base_spacing.0 > 0.0_f64 {
+            typeface.spaced(layout.base_spacing).draw(
+                segment.base,
+                font_size,
+                base_x,
+                baseline,
+                layer,
+            )?;
+        } else {
+            typeface.draw(segment.base, font_size, base_x, baseline, layer)?;
+        }
+        if let Some(ruby) = segment.ruby {
+            let ruby_x = x + width + layout.ruby_x;
+            typeface.draw(ruby, ruby_size, ruby_x, ruby_baseline, layer)?;
+        }
+        width += layout.width;
+    }
+    Ok(width)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The base & ruby of each segment.
+    fn pairs<'a>(segments: &[Segment<'a>]) -> Vec<(&'a str, Option<&'a str>)> {
+        segments
+            .iter()
+            .map(|segment| (segment.base, segment.ruby))
+            .collect()
+    }
+
+    /// Whether the width, base start, base spacing & ruby start of a layout are the expected
+    /// millimetres, give or take rounding.
+    fn matches(layout: &Layout, expected: [f64; 4]) -> bool {
+        [
+            layout.width,
+            layout.base_x,
+            layout.base_spacing,
+            layout.ruby_x,
+        ]
+        .iter()
+        .zip(expected)
+        .all(|(actual, expected)| (actual.0 - expected).abs() < 1e-9_f64)
+    }
+
+    #[test]
+    fn markup_annotates_its_base() {
+        assert!(
+            pairs(&parse_markup("私は{漢字|かんじ}です"))
+                == [("私は", None), ("漢字", Some("かんじ")), ("です", None)]
+        );
+    }
+
+    #[test]
+    fn invalid_markup_is_plain_text() {
+        assert!(strip_markup("{a}{b|c") == "{a}{b|c");
+        assert!(strip_markup("{山|やま}と{川}") == "山と{川}");
+    }
+
+    #[test]
+    fn groups_get_their_own_reading() {
+        assert!(
+            pairs(&pair_groups("山田　太郎", "やまだ たろう"))
+                == [
+                    ("山田", Some("やまだ")),
+                    ("\u{3000}", None),
+                    ("太郎", Some("たろう"))
+                ]
+        );
+    }
+
+    #[test]
+    fn mismatched_groups_share_the_whole_reading() {
+        assert!(
+            pairs(&pair_groups("山田太郎", "やまだ たろう"))
+                == [("山田太郎", Some("やまだ たろう"))]
+        );
+    }
+
+    #[test]
+    fn narrow_ruby_is_centered() {
+        assert!(matches(
+            &layout(Mm(10.0_f64), 2, Mm(6.0_f64)),
+            [10.0_f64, 0.0_f64, 0.0_f64, 2.0_f64]
+        ));
+    }
+
+    #[test]
+    fn wide_ruby_spreads_its_base() {
+        // A single character under a ruby three times as wide, such as 砥 read as とぎいし
+        let wide = layout(Mm(5.0_f64), 1, Mm(15.0_f64));
+        assert!(matches(&wide, [15.0_f64, 5.0_f64, 10.0_f64, 0.0_f64]));
+        // Both the base & the ruby stay within the segment, so neither overlaps the next one
+        assert!(wide.base_x.0 + 5.0_f64 <= wide.width.0);
+        assert!(wide.ruby_x.0 + 15.0_f64 <= wide.width.0);
+    }
+}
@@ -2,6 +2,8 @@
 
 use crate::yaml::{Entry, YAMLArgs};
 use anyhow::{anyhow, Result};
+use serde_yaml::Value;
+use std::path::PathBuf;
 
 pub(crate) fn handle_value<'a>(value: &'a String, inputs: &'a YAMLArgs) -> Result<&'a String> {
     if value.starts_with('$') {
@@ -37,7 +39,10 @@ pub(crate) fn handle_value<'a>(value: &'a String, inputs: &'a YAMLArgs) -> Resul
             "$teaching" => Ok(&inputs.teaching),
             "$affiliated_society" => Ok(&inputs.affiliated_society),
             "$notices" => Ok(&inputs.notices),
-            _ => Err(anyhow!("Unknown variable: {}", value)),
+            _ => match value.get(1..).and_then(|name| inputs.extra.get(name)) {
+                Some(Value::String(extra)) => Ok(extra),
+                _ => Err(anyhow!("Unknown variable: {}", value)),
+            },
         }
     } else {
         Ok(value)
@@ -56,3 +61,13 @@ pub(crate) fn handle_history_value<'a>(
         _ => Err(anyhow!("Unkown value: {}", value)),
     }
 }
+
+/// Resolves the path of an image, which is either a path or a variable holding one.
+/// Paths in the input file are relative to its directory.
+pub(crate) fn handle_path(value: &String, inputs: &YAMLArgs) -> Result<PathBuf> {
+    match value.as_str() {
+        "$photo" => Ok(inputs.photo.clone()),
+        _ if value.starts_with('$') => Ok(inputs.directory.join(handle_value(value, inputs)?)),
+        _ => Ok(PathBuf::from(value)),
+    }
+}
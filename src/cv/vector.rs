@@ -0,0 +1,264 @@
+//! Loads SVG images & PDF pages, which are drawn as form `XObject`s to keep them as vector art.
+//! SVG images are converted to a single page PDF first, so both are embedded the same way.
+
+use super::image::apply_opacity;
+use super::resources::Resources;
+use crate::style::core::{Fit, Point, Size};
+use anyhow::{anyhow, Context, Result};
+use printpdf::lopdf::content::Operation;
+use printpdf::lopdf::{dictionary, Dictionary, Document, Object, ObjectId, Stream};
+use printpdf::{PdfLayerReference, Pt};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use svg2pdf::usvg::{fontdb, PostProcessingSteps, Tree, TreeParsing, TreePostProc};
+
+/// A page of a PDF to draw as a form `XObject`.
+pub(crate) struct VectorImage {
+    document: Document,
+    page_id: ObjectId,
+    /// The visible area of the page, as the left, bottom, right & top in points.
+    bounding_box: [f64; 4],
+    /// The file the image was loaded from.
+    label: String,
+    /// The hash of the file & the number of the page, to add identical images only once.
+    digest: [u8; 32],
+}
+
+impl VectorImage {
+    fn width(&self) -> Pt {
+        Pt(self.bounding_box[2] - self.bounding_box[0])
+    }
+
+    fn height(&self) -> Pt {
+        Pt(self.bounding_box[3] - self.bounding_box[1])
+    }
+
+    pub(crate) fn label(&self) -> &str {
+        &self.label
+    }
+
+    pub(crate) fn digest(&self) -> [u8; 32] {
+        self.digest
+    }
+}
+
+/// Whether the contents of a file look like an SVG image.
+fn is_svg(path: &Path, contents: &[u8]) -> bool {
+    let extension = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(str::to_ascii_lowercase);
+    if matches!(extension.as_deref(), Some("svg" | "svgz")) {
+        return true;
+    }
+    let start = contents.get(..1024).unwrap_or(contents);
+    String::from_utf8_lossy(start).contains("<svg")
+}
+
+/// Converts an SVG image to a single page PDF, with its text turned into paths.
+fn svg_to_pdf(path: &Path, contents: &[u8]) -> Result<Vec<u8>> {
+    let options = svg2pdf::usvg::Options {
+        resources_dir: path.parent().map(Path::to_path_buf),
+        ..svg2pdf::usvg::Options::default()
+    };
+    let mut tree = Tree::from_data(contents, &options)
+        .map_err(|error| anyhow!("Failed to read the SVG image {}: {error}", path.display()))?;
+    let mut fonts = fontdb::Database::new();
+    fonts.load_system_fonts();
+    tree.postprocess(PostProcessingSteps::default(), &fonts);
+    Ok(svg2pdf::convert_tree(&tree, svg2pdf::Options::default()))
+}
+
+/// Looks up an entry of a page, which may be inherited from its parents in the page tree.
+fn inherited<'a>(document: &'a Document, page_id: ObjectId, key: &[u8]) -> Option<&'a Object> {
+    let mut node = document.get_dictionary(page_id).ok()?;
+    loop {
+        if let Ok(value) = node.get(key) {
+            return document.dereference(value).ok().map(|(_, object)| object);
+        }
+        let parent_id = node.get(b"Parent").and_then(Object::as_reference).ok()?;
+        node = document.get_dictionary(parent_id).ok()?;
+    }
+}
+
+/// Loads a page of a PDF, or the only page of an SVG image converted to PDF.
+/// Returns `None` for files that are neither, such as raster images.
+pub(crate) fn load_vector(path: &Path, page: Option<u32>) -> Result<Option<VectorImage>> {
+    let contents =
+        fs::read(path).with_context(|| format!("Failed to open the image {}", path.display()))?;
+    let page_number = page.unwrap_or(1);
+    let digest = Sha256::new()
+        .chain_update(&contents)
+        .chain_update(page_number.to_le_bytes())
+        .finalize()
+        .into();
+    let pdf = if contents.starts_with(b"%PDF") {
+        contents
+    } else if is_svg(path, &contents) {
+        svg_to_pdf(path, &contents)?
+    } else {
+        return Ok(None);
+    };
+    let document = Document::load_mem(&pdf)
+        .map_err(|error| anyhow!("Failed to read the PDF {}: {error}", path.display()))?;
+    let page_id = *document
+        .get_pages()
+        .get(&page_number)
+        .ok_or_else(|| anyhow!("There is no page {page_number} in {}", path.display()))?;
+    let visible_area = inherited(&document, page_id, b"CropBox")
+        .or_else(|| inherited(&document, page_id, b"MediaBox"))
+        .ok_or_else(|| anyhow!("Failed to find the size of the page in {}", path.display()))?;
+    let corners = visible_area
+        .as_array()?
+        .iter()
+        .map(Object::as_float)
+        .collect::<Result<Vec<f64>, _>>()?;
+    let bounding_box: [f64; 4] = corners
+        .try_into()
+        .map_err(|_corners| anyhow!("Invalid page size in {}", path.display()))?;
+    Ok(Some(VectorImage {
+        document,
+        page_id,
+        bounding_box,
+        label: path.display().to_string(),
+        digest,
+    }))
+}
+
+/// Copies an object from another document, along with every object it refers to.
+#[allow(unused_results, reason = "every object is only imported once")]
+fn import_object(
+    document: &mut Document,
+    source: &Document,
+    object: &Object,
+    imported: &mut HashMap<ObjectId, ObjectId>,
+) -> Result<Object> {
+    Ok(match object {
+        Object::Reference(source_id) => {
+            if let Some(id) = imported.get(source_id) {
+                return Ok(Object::Reference(*id));
+            }
+            let id = document.new_object_id();
+            imported.insert(*source_id, id);
+            let copy = import_object(document, source, source.get_object(*source_id)?, imported)?;
+            document.objects.insert(id, copy);
+            Object::Reference(id)
+        }
+        Object::Array(items) => Object::Array(
+            items
+                .iter()
+                .map(|item| import_object(document, source, item, imported))
+                .collect::<Result<_>>()?,
+        ),
+        Object::Dictionary(dictionary) => {
+            Object::Dictionary(import_dictionary(document, source, dictionary, imported)?)
+        }
+        Object::Stream(stream) => Object::Stream(Stream::new(
+            import_dictionary(document, source, &stream.dict, imported)?,
+            stream.content.clone(),
+        )),
+        _ => object.clone(),
+    })
+}
+
+fn import_dictionary(
+    document: &mut Document,
+    source: &Document,
+    dictionary: &Dictionary,
+    imported: &mut HashMap<ObjectId, ObjectId>,
+) -> Result<Dictionary> {
+    let mut copy = Dictionary::new();
+    for (key, value) in dictionary {
+        copy.set(
+            key.clone(),
+            import_object(document, source, value, imported)?,
+        );
+    }
+    Ok(copy)
+}
+
+/// Adds the page of a vector image to a document as a form `XObject`, returning its ID.
+pub(crate) fn import_form(document: &mut Document, vector: &VectorImage) -> Result<ObjectId> {
+    let source = &vector.document;
+    let content = source.get_page_content(vector.page_id)?;
+    let mut imported = HashMap::new();
+    let resources = match inherited(source, vector.page_id, b"Resources") {
+        Some(resources) => import_object(document, source, resources, &mut imported)?,
+        None => Object::Dictionary(Dictionary::new()),
+    };
+    let bounding_box: Vec<Object> = vector.bounding_box.into_iter().map(Object::Real).collect();
+    let mut form = Stream::new(
+        dictionary! {
+            "Type" => "XObject",
+            "Subtype" => "Form",
+            "BBox" => bounding_box,
+            "Resources" => resources,
+        },
+        content,
+    );
+    form.compress()?;
+    Ok(document.add_object(form))
+}
+
+/// Draws a vector image fitted into a frame, whose bottom left corner is `frame_position`.
+/// Covering the frame clips the parts of the image outside of it.
+pub(crate) fn place_vector(
+    vector: VectorImage,
+    frame_position: Point,
+    frame: Size,
+    fit: Fit,
+    opacity: Option<f64>,
+    resources: &Resources,
+    layer: &PdfLayerReference,
+) {
+    let (frame_width, frame_height) = (Pt::from(frame.width).0, Pt::from(frame.height).0);
+    let (width, height) = (vector.width().0, vector.height().0);
+    let (scale_x, scale_y) = match fit {
+        Fit::Stretch => (frame_width / width, frame_height / height),
+        Fit::Contain => {
+            let scale = (frame_width / width).min(frame_height / height);
+            (scale, scale)
+        }
+        Fit::Cover => {
+            let scale = (frame_width / width).max(frame_height / height);
+            (scale, scale)
+        }
+    };
+    // Center the image in the frame
+    let x = Pt::from(frame_position.x).0 + width.mul_add(-scale_x, frame_width) / 2.0_f64;
+    let y = Pt::from(frame_position.y).0 + height.mul_add(-scale_y, frame_height) / 2.0_f64;
+    let [left, bottom, _, _] = vector.bounding_box;
+    let name = resources.add_form(vector);
+    layer.save_graphics_state();
+    apply_opacity(opacity, resources, layer);
+    if fit == Fit::Cover {
+        let clip = [
+            Pt::from(frame_position.x).0,
+            Pt::from(frame_position.y).0,
+            frame_width,
+            frame_height,
+        ];
+        layer.add_operation(Operation::new(
+            "re",
+            clip.into_iter().map(Object::Real).collect(),
+        ));
+        layer.add_operation(Operation::new("W", Vec::new()));
+        layer.add_operation(Operation::new("n", Vec::new()));
+    }
+    let matrix = [
+        scale_x,
+        0.0_f64,
+        0.0_f64,
+        scale_y,
+        left.mul_add(-scale_x, x),
+        bottom.mul_add(-scale_y, y),
+    ];
+    layer.add_operation(Operation::new(
+        "cm",
+        matrix.into_iter().map(Object::Real).collect(),
+    ));
+    layer.add_operation(Operation::new("Do", vec![Object::Name(name.into_bytes())]));
+    layer.restore_graphics_state();
+}
@@ -0,0 +1,167 @@
+//! Lays out text in vertical columns (tategaki).
+
+use super::font::{is_variation_selector, LoadedFont, Typeface};
+use super::MARGIN;
+use crate::style::core::Point;
+use printpdf::{Mm, PdfLayerReference, Pt};
+
+/// The longest run of digits set side by side in a single cell (tate-chu-yoko).
+/// Longer runs are set upright, one digit per cell.
+const MAX_TATE_CHU_YOKO_DIGITS: usize = 2;
+
+/// What is drawn in a single cell of a column.
+enum Cell<'a> {
+    /// A character standing upright, along with the variation selector after it.
+    Upright(&'a str),
+    /// A short run of digits set horizontally across the column (tate-chu-yoko).
+    TateChuYoko(&'a str),
+}
+
+fn split_cells(line: &str) -> Vec<Cell<'_>> {
+    let mut cells = Vec::new();
+    let mut chars = line.char_indices().peekable();
+    while let Some((start, c)) = chars.next() {
+        if !c.is_ascii_digit() {
+            let mut end = start + c.len_utf8();
+            if let Some((index, selector)) = chars.next_if(|(_, next)| is_variation_selector(*next))
+            {
+                end = index + selector.len_utf8();
+            }
+            cells.push(Cell::Upright(line.get(start..end).unwrap_or_default()));
+            continue;
+        }
+        let mut end = start + c.len_utf8();
+        while let Some((index, next)) = chars.next_if(|(_, next)| next.is_ascii_digit()) {
+            end = index + next.len_utf8();
+        }
+        let digits = line.get(start..end).unwrap_or_default();
+        if digits.len() <= MAX_TATE_CHU_YOKO_DIGITS {
+            cells.push(Cell::TateChuYoko(digits));
+        } else {
+            cells.extend(
+                digits
+                    .char_indices()
+                    .map(|(index, _)| Cell::Upright(digits.get(index..=index).unwrap_or_default())),
+            );
+        }
+    }
+    cells
+}
+
+/// The length of a single column of text, with `letter_spacing` after every cell.
+pub(crate) fn column_height(line: &str, font_size: f64, letter_spacing: Mm) -> Mm {
+    let cell_count = split_cells(line)
+        .iter()
+        .fold(0.0_f64, |count, _| count + 1.0_f64);
+    (Mm::from(Pt(font_size)) + letter_spacing) * cell_count
+}
+
+fn draw_upright(
+    cell: &str,
+    left: Mm,
+    baseline: Mm,
+    font: &LoadedFont,
+    font_size: f64,
+    layer: &PdfLayerReference,
+) {
+    let em = Mm::from(Pt(font_size));
+    let width = font.text_width(cell, font_size);
+    let x = left + (em - width) / 2.0_f64;
+    layer.begin_text_section();
+    layer.set_font(&font.reference, font_size);
+    layer.set_text_cursor(x, baseline);
+    font.write_vertical(cell, layer);
+    layer.end_text_section();
+}
+
+fn draw_tate_chu_yoko(
+    text: &str,
+    left: Mm,
+    baseline: Mm,
+    font: &LoadedFont,
+    font_size: f64,
+    layer: &PdfLayerReference,
+) {
+    let em = Mm::from(Pt(font_size));
+    let width = font.text_width(text, font_size);
+    // Squeeze the digits horizontally so they stay inside the column
+    let scale = if width > em { em / width } else { 1.0_f64 };
+    let x = left + (em - width * scale) / 2.0_f64;
+    layer.save_graphics_state();
+    layer.begin_text_section();
+    layer.set_font(&font.reference, font_size);
+    layer.set_text_scaling(scale * 100.0_f64);
+    layer.set_text_cursor(x, baseline);
+    font.write(text, layer);
+    layer.end_text_section();
+    layer.restore_graphics_state();
+}
+
+/// Draws each line of the text as a column, with the first column's top left corner at `position`.
+/// Following columns are placed to the left of it, `line_height` times the font size apart.
+/// Each cell is drawn with the first font of the fallback chain that has its characters,
+/// followed by the typeface's letter spacing.
+pub(crate) fn draw_vertical_text(
+    value: &str,
+    position: Point,
+    typeface: &Typeface<'_>,
+    font_size: f64,
+    line_height: f64,
+    layer: &PdfLayerReference,
+) {
+    let em = Mm::from(Pt(font_size));
+    // Fit the font's full height into each cell
+    let ascent = typeface.font.ascent(font_size);
+    let descent = typeface.font.descent(font_size);
+    let baseline_offset = em * (ascent / (ascent - descent));
+    let mut left = position.x + MARGIN;
+    for line in value.split('\n') {
+        let mut top = position.y + MARGIN;
+        for cell in split_cells(line) {
+            let baseline = top - baseline_offset;
+            match cell {
+                Cell::Upright(text) => {
+                    let font = typeface.for_cell(text).font;
+                    draw_upright(text, left, baseline, font, font_size, layer);
+                }
+                Cell::TateChuYoko(text) => {
+                    let font = typeface.for_cell(text.get(..1).unwrap_or_default()).font;
+                    draw_tate_chu_yoko(text, left, baseline, font, font_size, layer);
+                }
+            }
+            top -= em + typeface.letter_spacing;
+        }
+        left -= em * line_height;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The text of each cell of a line, with tate-chu-yoko cells in brackets.
+    fn cell_texts(line: &str) -> Vec<String> {
+        split_cells(line)
+            .into_iter()
+            .map(|cell| match cell {
+                Cell::Upright(text) => text.to_owned(),
+                Cell::TateChuYoko(text) => format!("[{text}]"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn short_numbers_are_set_across() {
+        assert!(cell_texts("令和5年12月") == ["令", "和", "[5]", "年", "[12]", "月"]);
+    }
+
+    #[test]
+    fn long_numbers_are_set_upright() {
+        assert!(cell_texts("2024年") == ["2", "0", "2", "4", "年"]);
+    }
+
+    #[test]
+    fn variation_selectors_stay_with_their_character() {
+        assert!(cell_texts("辻\u{E0100}町") == ["辻\u{E0100}", "町"]);
+    }
+}
@@ -38,7 +38,6 @@
     clippy::same_name_method,
     clippy::shadow_unrelated,
     clippy::str_to_string,
-    clippy::string_to_string,
     clippy::todo,
     clippy::try_err,
     clippy::undocumented_unsafe_blocks,
@@ -53,7 +52,6 @@
 )]
 #![deny(
     missing_abi,
-    pointer_structural_match,
     unsafe_op_in_unsafe_fn,
     clippy::default_numeric_fallback,
     clippy::float_cmp_const,
@@ -77,13 +75,48 @@ mod yaml;
 
 fn main() -> Result<()> {
     let cli = args::Args::parse();
+    #[cfg(feature = "bundled-fonts")]
+    if cli.font_license {
+        print!("{}", cv::BUNDLED_FONT_LICENSE);
+        return Ok(());
+    }
 
     let raw_input_file = read_to_string(&cli.input)?;
-    let input_file: yaml::YAMLArgs = from_str(&raw_input_file)?;
+    let mut input_file: yaml::YAMLArgs = from_str(&raw_input_file)?;
+    input_file.resolve_paths(&cli.input);
 
-    let style_file = style::read(&cli.style)?;
+    let mut style_file = match &cli.font_config {
+        Some(font_config) => style::read_font_config(font_config)?,
+        None => Vec::new(),
+    };
+    style_file.extend(style::read(&cli.style)?);
 
-    cv::make(cli.output.as_path(), style_file, &input_file)?;
+    let options = cv::Options {
+        autolink: cli.autolink,
+        underline_links: cli.underline_links,
+        image_dpi: cli.image_dpi,
+        jpeg_quality: cli.jpeg_quality,
+        max_size: cli.max_size,
+        font_directories: cli.font_directories,
+        outline_text: cli.outline_text,
+        text_layer: cli.text_layer,
+        metadata: cv::MetadataOptions {
+            title: cli.title,
+            author: cli.author,
+            subject: cli.subject,
+            keywords: cli.keywords,
+            creator: cli.creator,
+            date: cli.date,
+        },
+        pdfa: cli.pdfa,
+        encryption: style::command::Encryption {
+            user_password: cli.user_password,
+            owner_password: cli.owner_password,
+            permissions: cli.allow,
+        },
+        deterministic: cli.deterministic,
+    };
+    cv::make(cli.output.as_path(), style_file, &input_file, &options)?;
 
     println!(
         "input  file: {}\nstyle  file: {}\noutput file: {}\nDone.",
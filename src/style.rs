@@ -6,15 +6,18 @@ use std::fs::File;
 use std::io::{BufRead, BufReader};
 use std::iter::Enumerate;
 use std::num::ParseFloatError;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::vec::Vec;
 pub(crate) mod command;
 pub(crate) mod core;
 use crate::style::command::{
-    EducationExperience, History, HistoryPosition, Line, Lines, MiscBox, MultiLines, Photo, Text,
-    TextBox, YMBox,
+    EducationExperience, Encryption, Font, FontFallback, History, HistoryPosition, Image, Line,
+    Lines, Link, MiscBox, MultiLines, Photo, QrCode, Ruby, Text, TextBox, YMBox,
+};
+use crate::style::core::{
+    Align, Direction, ErrorCorrection, Fit, FontOptions, FontStyle, FontWeight, LineOptions,
+    LineStyle, Overflow, Permission, Point, Size,
 };
-use crate::style::core::{FontOptions, LineOptions, LineStyle, Point, Size};
 
 fn handle_missing<T>(
     expression: Option<T>,
@@ -22,16 +25,13 @@ fn handle_missing<T>(
     command_name: &str,
     line_number: usize,
 ) -> T {
-    let message = format!(
-        "Missing {} value for {} at line: {}",
-        value_name, command_name, line_number
-    );
+    let message = format!("Missing {value_name} value for {command_name} at line: {line_number}");
     expression.expect(&message)
 }
 
 fn parse_size(raw_size: &str) -> Result<Mm, ParseFloatError> {
     let len = raw_size.len();
-    let prefix = &raw_size.get(len - 2..);
+    let prefix = &len.checked_sub(2).and_then(|start| raw_size.get(start..));
     match prefix {
         Some("mm") => {
             let mm_number = raw_size.trim_end_matches("mm");
@@ -51,7 +51,7 @@ fn parse_size(raw_size: &str) -> Result<Mm, ParseFloatError> {
 }
 
 fn parse_option<T: std::str::FromStr>(name: &str, raw_option: &str) -> Result<T, T::Err> {
-    let pattern = format!("{}=", name);
+    let pattern = format!("{name}=");
     let option_number = raw_option.trim_start_matches(&pattern);
     let option_value = option_number.parse::<T>()?;
     Ok(option_value)
@@ -60,18 +60,45 @@ fn parse_option<T: std::str::FromStr>(name: &str, raw_option: &str) -> Result<T,
 fn parse_font_options(parameters: &[&str]) -> Result<FontOptions> {
     let mut font_size: Option<f64> = None;
     let mut font_face: Option<String> = None;
+    let mut direction: Option<Direction> = None;
+    let mut font_weight: Option<FontWeight> = None;
+    let mut font_style: Option<FontStyle> = None;
+    let mut synthetic_bold: Option<bool> = None;
+    let mut align: Option<Align> = None;
+    let mut width: Option<Mm> = None;
+    let mut letter_spacing: Option<Mm> = None;
+    let mut line_height: Option<f64> = None;
+    let mut markup: Option<bool> = None;
     for parameter in parameters {
         if let Some((command, value)) = (*parameter).to_owned().split_once('=') {
             match command {
                 "font_size" => font_size = Some(value.parse::<f64>()?),
                 "font_face" => font_face = Some(value.to_owned()),
-                _ => continue,
+                "direction" => direction = Some(value.parse::<Direction>()?),
+                "font_weight" => font_weight = Some(value.parse::<FontWeight>()?),
+                "font_style" => font_style = Some(value.parse::<FontStyle>()?),
+                "synthetic_bold" => synthetic_bold = Some(value.parse::<bool>()?),
+                "align" => align = Some(value.parse::<Align>()?),
+                "width" => width = Some(parse_size(value)?),
+                "letter_spacing" => letter_spacing = Some(parse_size(value)?),
+                "line_height" => line_height = Some(value.parse::<f64>()?),
+                "markup" => markup = Some(value.parse::<bool>()?),
+                _ => {}
             }
         }
     }
     Ok(FontOptions {
         font_size,
         font_face,
+        direction,
+        font_weight,
+        font_style,
+        synthetic_bold,
+        align,
+        width,
+        letter_spacing,
+        line_height,
+        markup,
     })
 }
 
@@ -83,7 +110,7 @@ fn parse_line_options(parameters: &[&str]) -> Result<LineOptions> {
             match command {
                 "line_width" => line_width = Some(value.parse::<f32>()?),
                 "line_style" => line_style = Some(value.parse::<LineStyle>()?),
-                _ => continue,
+                _ => {}
             }
         }
     }
@@ -93,6 +120,23 @@ fn parse_line_options(parameters: &[&str]) -> Result<LineOptions> {
     })
 }
 
+fn parse_overflow(parameters: &[&str]) -> Result<Option<Overflow>> {
+    for parameter in parameters {
+        if let Some(("overflow", value)) = parameter.split_once('=') {
+            return Ok(Some(value.parse::<Overflow>()?));
+        }
+    }
+    Ok(None)
+}
+
+fn parse_link_option(parameters: &[&str]) -> Option<String> {
+    parameters.iter().find_map(|parameter| {
+        parameter
+            .strip_prefix("link=")
+            .map(std::borrow::ToOwned::to_owned)
+    })
+}
+
 fn parse_string(parameters: &[&str], line_number: usize) -> Result<Text> {
     let raw_x = *handle_missing(parameters.get(1), "x", "string", line_number);
     let raw_y = *handle_missing(parameters.get(2), "y", "string", line_number);
@@ -105,6 +149,24 @@ fn parse_string(parameters: &[&str], line_number: usize) -> Result<Text> {
         position,
         value: raw_value.to_owned(),
         font_options: parse_font_options(parameters)?,
+        link: parse_link_option(parameters),
+    })
+}
+
+fn parse_ruby(parameters: &[&str], line_number: usize) -> Result<Ruby> {
+    let raw_x = *handle_missing(parameters.get(1), "x", "ruby", line_number);
+    let raw_y = *handle_missing(parameters.get(2), "y", "ruby", line_number);
+    let raw_value = *handle_missing(parameters.get(3), "value", "ruby", line_number);
+    let raw_reading = *handle_missing(parameters.get(4), "reading", "ruby", line_number);
+    let position = Point {
+        x: parse_size(raw_x)?,
+        y: parse_size(raw_y)?,
+    };
+    Ok(Ruby {
+        position,
+        value: raw_value.to_owned(),
+        reading: raw_reading.to_owned(),
+        font_options: parse_font_options(parameters)?,
     })
 }
 
@@ -148,7 +210,16 @@ fn parse_box(parameters: &[&str], line_number: usize) -> Result<command::Box> {
     })
 }
 
-fn parse_photo(parameters: &[&str], line_number: usize) -> Result<Photo, ParseFloatError> {
+fn parse_fit(parameters: &[&str]) -> Result<Option<Fit>> {
+    for parameter in parameters {
+        if let Some(("fit", value)) = parameter.split_once('=') {
+            return Ok(Some(value.parse::<Fit>()?));
+        }
+    }
+    Ok(None)
+}
+
+fn parse_photo(parameters: &[&str], line_number: usize) -> Result<Photo> {
     let raw_pos_x = *handle_missing(parameters.get(1), "x", "photo", line_number);
     let raw_pos_y = *handle_missing(parameters.get(2), "y", "photo", line_number);
     let raw_width = *handle_missing(parameters.get(3), "width", "photo", line_number);
@@ -161,7 +232,104 @@ fn parse_photo(parameters: &[&str], line_number: usize) -> Result<Photo, ParseFl
         width: parse_size(raw_width)?,
         height: parse_size(raw_height)?,
     };
-    Ok(Photo { position, size })
+    let placeholder = parameters.iter().find_map(|parameter| {
+        parameter
+            .strip_prefix("placeholder=")
+            .map(std::borrow::ToOwned::to_owned)
+    });
+    Ok(Photo {
+        position,
+        size,
+        fit: parse_fit(parameters)?,
+        placeholder,
+        line_options: parse_line_options(parameters)?,
+        font_options: parse_font_options(parameters)?,
+    })
+}
+
+fn parse_opacity(parameters: &[&str]) -> Result<Option<f64>> {
+    for parameter in parameters {
+        if let Some(("opacity", value)) = parameter.split_once('=') {
+            let opacity = value.parse::<f64>()?;
+            if !(0.0_f64..=1.0_f64).contains(&opacity) {
+                return Err(anyhow!("Opacity must be between 0 and 1, got {opacity}"));
+            }
+            return Ok(Some(opacity));
+        }
+    }
+    Ok(None)
+}
+
+fn parse_page(parameters: &[&str]) -> Result<Option<u32>> {
+    for parameter in parameters {
+        if let Some(("page", value)) = parameter.split_once('=') {
+            let page = value.parse::<u32>()?;
+            if page == 0 {
+                return Err(anyhow!("Pages are numbered from 1"));
+            }
+            return Ok(Some(page));
+        }
+    }
+    Ok(None)
+}
+
+/// Parses an `image` command. Literal paths are relative to the style file's directory.
+fn parse_image(parameters: &[&str], line_number: usize, directory: &Path) -> Result<Image> {
+    let raw_pos_x = *handle_missing(parameters.get(1), "x", "image", line_number);
+    let raw_pos_y = *handle_missing(parameters.get(2), "y", "image", line_number);
+    let raw_width = *handle_missing(parameters.get(3), "width", "image", line_number);
+    let raw_height = *handle_missing(parameters.get(4), "height", "image", line_number);
+    let raw_path = *handle_missing(parameters.get(5), "path", "image", line_number);
+    let position = Point {
+        x: parse_size(raw_pos_x)?,
+        y: parse_size(raw_pos_y)?,
+    };
+    let size = Size {
+        width: parse_size(raw_width)?,
+        height: parse_size(raw_height)?,
+    };
+    let path = if raw_path.starts_with('$') {
+        raw_path.to_owned()
+    } else {
+        directory.join(raw_path).display().to_string()
+    };
+    Ok(Image {
+        position,
+        size,
+        path,
+        fit: parse_fit(parameters)?,
+        opacity: parse_opacity(parameters)?,
+        page: parse_page(parameters)?,
+    })
+}
+
+fn parse_qrcode(parameters: &[&str], line_number: usize) -> Result<QrCode> {
+    let raw_pos_x = *handle_missing(parameters.get(1), "x", "QR code", line_number);
+    let raw_pos_y = *handle_missing(parameters.get(2), "y", "QR code", line_number);
+    let raw_size = *handle_missing(parameters.get(3), "size", "QR code", line_number);
+    let raw_value = *handle_missing(parameters.get(4), "value", "QR code", line_number);
+    let position = Point {
+        x: parse_size(raw_pos_x)?,
+        y: parse_size(raw_pos_y)?,
+    };
+    let mut error_correction = None;
+    let mut quiet_zone = None;
+    for parameter in parameters {
+        if let Some((command, value)) = parameter.split_once('=') {
+            match command {
+                "ec" => error_correction = Some(value.parse::<ErrorCorrection>()?),
+                "quiet_zone" => quiet_zone = Some(value.parse::<u32>()?),
+                _ => {}
+            }
+        }
+    }
+    Ok(QrCode {
+        position,
+        size: parse_size(raw_size)?,
+        value: raw_value.to_owned(),
+        error_correction,
+        quiet_zone,
+    })
 }
 
 fn parse_textbox(parameters: &[&str], line_number: usize) -> Result<TextBox> {
@@ -183,9 +351,79 @@ fn parse_textbox(parameters: &[&str], line_number: usize) -> Result<TextBox> {
         size,
         value: raw_value.to_owned(),
         font_options: parse_font_options(parameters)?,
+        overflow: parse_overflow(parameters)?,
+        link: parse_link_option(parameters),
+        line_number,
+    })
+}
+
+fn parse_link(parameters: &[&str], line_number: usize) -> Result<Link> {
+    let raw_pos_x = *handle_missing(parameters.get(1), "x", "link", line_number);
+    let raw_pos_y = *handle_missing(parameters.get(2), "y", "link", line_number);
+    let raw_width = *handle_missing(parameters.get(3), "width", "link", line_number);
+    let raw_height = *handle_missing(parameters.get(4), "height", "link", line_number);
+    let raw_target = *handle_missing(parameters.get(5), "target", "link", line_number);
+    let position = Point {
+        x: parse_size(raw_pos_x)?,
+        y: parse_size(raw_pos_y)?,
+    };
+    let size = Size {
+        width: parse_size(raw_width)?,
+        height: parse_size(raw_height)?,
+    };
+    Ok(Link {
+        position,
+        size,
+        target: raw_target.to_owned(),
     })
 }
 
+fn parse_font(parameters: &[&str], line_number: usize, directory: &Path) -> Font {
+    let alias = *handle_missing(parameters.get(1), "alias", "font", line_number);
+    let path = *handle_missing(parameters.get(2), "path", "font", line_number);
+    Font {
+        alias: alias.to_owned(),
+        path: path.to_owned(),
+        directory: directory.to_path_buf(),
+    }
+}
+
+fn parse_font_directory(parameters: &[&str], line_number: usize, directory: &Path) -> PathBuf {
+    let path = *handle_missing(parameters.get(1), "path", "font directory", line_number);
+    directory.join(path)
+}
+
+fn parse_font_fallback(parameters: &[&str], line_number: usize) -> FontFallback {
+    let face = *handle_missing(parameters.get(1), "font face", "font fallback", line_number);
+    // At least one fallback is needed
+    let _first = handle_missing(parameters.get(2), "fallback", "font fallback", line_number);
+    FontFallback {
+        face: face.to_owned(),
+        fallbacks: parameters
+            .iter()
+            .skip(2)
+            .map(|fallback| (*fallback).to_owned())
+            .collect(),
+    }
+}
+
+fn parse_encryption(parameters: &[&str]) -> Result<Encryption> {
+    let mut encryption = Encryption::default();
+    for parameter in parameters.iter().skip(1) {
+        match parameter.split_once('=') {
+            Some(("user_password", value)) => encryption.user_password = Some(value.to_owned()),
+            Some(("owner_password", value)) => encryption.owner_password = Some(value.to_owned()),
+            // Each allowed action is given as its own parameter
+            Some(("allow", value)) => encryption
+                .permissions
+                .get_or_insert_with(Vec::new)
+                .push(value.parse::<Permission>()?),
+            _ => return Err(anyhow!("Unsupported encrypt parameter: {parameter}")),
+        }
+    }
+    Ok(encryption)
+}
+
 fn parse_multilines(parameters: &[&str], line_number: usize) -> Result<MultiLines> {
     let raw_pos_x = *handle_missing(parameters.get(1), "x", "multi-lines", line_number);
     let raw_pos_y = *handle_missing(parameters.get(2), "y", "multi-lines", line_number);
@@ -245,6 +483,8 @@ fn parse_miscbox(parameters: &[&str], line_number: usize) -> Result<MiscBox> {
         height: parse_size(raw_height)?,
         value: raw_value.to_owned(),
         font_options: parse_font_options(parameters)?,
+        overflow: parse_overflow(parameters)?,
+        line_number,
     })
 }
 
@@ -326,9 +566,11 @@ fn parse_lines(parameters: &[&str], line_number: usize) -> Result<Lines> {
 
 pub(crate) enum Command {
     Text(Text),
+    Ruby(Ruby),
     Line(Line),
     Box(command::Box),
     Photo(Photo),
+    Image(Image),
     NewPage,
     TextBox(TextBox),
     MultiLines(MultiLines),
@@ -337,6 +579,13 @@ pub(crate) enum Command {
     History(History),
     EducationExperience(EducationExperience),
     Lines(Lines),
+    Link(Link),
+    QrCode(QrCode),
+    Font(Font),
+    FontFallback(FontFallback),
+    /// A directory to look for fonts in.
+    FontDirectory(PathBuf),
+    Encryption(Encryption),
 }
 
 type LineIterator = Enumerate<std::io::Lines<BufReader<File>>>;
@@ -346,10 +595,16 @@ fn get_lines(path: &PathBuf) -> std::io::Result<LineIterator> {
     Ok(reader.lines().enumerate())
 }
 
+#[allow(
+    clippy::too_many_lines,
+    reason = "every command of the style file has its own arm"
+)]
 pub(crate) fn read(path: &PathBuf) -> Result<Vec<Command>> {
     let mut items: Vec<Command> = Vec::new();
+    let directory = path.parent().unwrap_or_else(|| Path::new(""));
     for (index, line) in get_lines(path)? {
         let line = line?;
+        let line_number = index + 1;
         // Handle comments
         if line.starts_with('#') {
             continue;
@@ -362,52 +617,84 @@ pub(crate) fn read(path: &PathBuf) -> Result<Vec<Command>> {
         let command_name = split_line.first();
         match command_name {
             Some(&"string") => {
-                let string = parse_string(&split_line, index)?;
+                let string = parse_string(&split_line, line_number)?;
                 items.push(Command::Text(string));
             }
+            Some(&"ruby") => {
+                let ruby = parse_ruby(&split_line, line_number)?;
+                items.push(Command::Ruby(ruby));
+            }
             Some(&"line") => {
-                let line_command = parse_line(&split_line, index)?;
+                let line_command = parse_line(&split_line, line_number)?;
                 items.push(Command::Line(line_command));
             }
             Some(&"box") => {
-                let box_command = parse_box(&split_line, index)?;
+                let box_command = parse_box(&split_line, line_number)?;
                 items.push(Command::Box(box_command));
             }
             Some(&"photo") => {
-                let photo = parse_photo(&split_line, index)?;
+                let photo = parse_photo(&split_line, line_number)?;
                 items.push(Command::Photo(photo));
             }
+            Some(&"image") => {
+                let image = parse_image(&split_line, line_number, directory)?;
+                items.push(Command::Image(image));
+            }
             Some(&"new_page") => {
                 items.push(Command::NewPage);
             }
             Some(&"textbox") => {
-                let textbox = parse_textbox(&split_line, index)?;
+                let textbox = parse_textbox(&split_line, line_number)?;
                 items.push(Command::TextBox(textbox));
             }
             Some(&"multi_lines") => {
-                let multi_lines = parse_multilines(&split_line, index)?;
+                let multi_lines = parse_multilines(&split_line, line_number)?;
                 items.push(Command::MultiLines(multi_lines));
             }
             Some(&"ymbox") => {
-                let ymbox = parse_ymbox(&split_line, index)?;
+                let ymbox = parse_ymbox(&split_line, line_number)?;
                 items.push(Command::YMBox(ymbox));
             }
             Some(&"miscbox") => {
-                let miscbox = parse_miscbox(&split_line, index)?;
+                let miscbox = parse_miscbox(&split_line, line_number)?;
                 items.push(Command::MiscBox(miscbox));
             }
             Some(&"history") => {
-                let history = parse_history(&split_line, index)?;
+                let history = parse_history(&split_line, line_number)?;
                 items.push(Command::History(history));
             }
             Some(&"education_experience") => {
-                let education_experience = parse_education_experience(&split_line, index)?;
+                let education_experience = parse_education_experience(&split_line, line_number)?;
                 items.push(Command::EducationExperience(education_experience));
             }
             Some(&"lines") => {
-                let lines = parse_lines(&split_line, index)?;
+                let lines = parse_lines(&split_line, line_number)?;
                 items.push(Command::Lines(lines));
             }
+            Some(&"link") => {
+                let link_command = parse_link(&split_line, line_number)?;
+                items.push(Command::Link(link_command));
+            }
+            Some(&"font") => {
+                let font = parse_font(&split_line, line_number, directory);
+                items.push(Command::Font(font));
+            }
+            Some(&"font_fallback") => {
+                let font_fallback = parse_font_fallback(&split_line, line_number);
+                items.push(Command::FontFallback(font_fallback));
+            }
+            Some(&"font_dir") => {
+                let font_directory = parse_font_directory(&split_line, line_number, directory);
+                items.push(Command::FontDirectory(font_directory));
+            }
+            Some(&"encrypt") => {
+                let encryption = parse_encryption(&split_line)?;
+                items.push(Command::Encryption(encryption));
+            }
+            Some(&"qrcode") => {
+                let qrcode = parse_qrcode(&split_line, line_number)?;
+                items.push(Command::QrCode(qrcode));
+            }
             _ => {
                 return Err(anyhow!(
                     "Unsupported command: {}!",
@@ -418,3 +705,20 @@ pub(crate) fn read(path: &PathBuf) -> Result<Vec<Command>> {
     }
     Ok(items)
 }
+
+/// Reads a font config file, which registers fonts with the same commands as the style file.
+pub(crate) fn read_font_config(path: &PathBuf) -> Result<Vec<Command>> {
+    let commands = read(path)?;
+    if commands.iter().any(|command| {
+        !matches!(
+            command,
+            Command::Font(_) | Command::FontFallback(_) | Command::FontDirectory(_)
+        )
+    }) {
+        return Err(anyhow!(
+            "Only font, font_fallback & font_dir commands are allowed in the font config file {}",
+            path.display()
+        ));
+    }
+    Ok(commands)
+}
@@ -1,15 +1,21 @@
 //! The commands supported in the style file
 
-use crate::style::core::{FontOptions, LineOptions, Point, Size};
+use crate::style::core::{
+    ErrorCorrection, Fit, FontOptions, LineOptions, Overflow, Permission, Point, Size,
+    DEFAULT_PHOTO_PLACEHOLDER, DEFAULT_QUIET_ZONE,
+};
 use printpdf::Mm;
 use std::fmt::Result as FmtResult;
 use std::fmt::{Display, Formatter};
+use std::path::PathBuf;
 
 /// A string.
 pub(crate) struct Text {
     pub(crate) position: Point,
     pub(crate) value: String,
     pub(crate) font_options: FontOptions,
+    /// Where clicking the string links to.
+    pub(crate) link: Option<String>,
 }
 
 impl Display for Text {
@@ -22,7 +28,29 @@ impl Display for Text {
     }
 }
 
+/// A string with furigana (ruby) set above it.
+pub(crate) struct Ruby {
+    pub(crate) position: Point,
+    pub(crate) value: String,
+    pub(crate) reading: String,
+    pub(crate) font_options: FontOptions,
+}
+
+impl Display for Ruby {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(
+            f,
+            "({}, {}, {}, {})",
+            self.position, self.value, self.reading, self.font_options
+        )
+    }
+}
+
 /// A line.
+#[allow(
+    clippy::struct_field_names,
+    reason = "the field names match the style file"
+)]
 pub(crate) struct Line {
     pub(crate) start_position: Point,
     pub(crate) end_position: Point,
@@ -56,15 +84,173 @@ impl Display for Box {
     }
 }
 
+/// A font file registered under an alias, to be used as a `font_face`.
+pub(crate) struct Font {
+    pub(crate) alias: String,
+    /// A path, file name or font name to look for in the font directories.
+    pub(crate) path: String,
+    /// The directory of the file the font was registered in.
+    pub(crate) directory: PathBuf,
+}
+
+impl Display for Font {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "({}, {})", self.alias, self.path)
+    }
+}
+
+/// The fonts to draw the characters a font face has no glyphs for with, in order.
+pub(crate) struct FontFallback {
+    pub(crate) face: String,
+    pub(crate) fallbacks: Vec<String>,
+}
+
+impl Display for FontFallback {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "({}, {})", self.face, self.fallbacks.join(" → "))
+    }
+}
+
+/// The passwords & permissions to encrypt the PDF with.
+/// The PDF is encrypted once any of them is set.
+#[derive(Clone, Default)]
+pub(crate) struct Encryption {
+    /// The password needed to open the PDF.
+    pub(crate) user_password: Option<String>,
+    /// The password that lifts the restrictions on the PDF.
+    pub(crate) owner_password: Option<String>,
+    /// What can be done without the owner password, only printing unless set.
+    pub(crate) permissions: Option<Vec<Permission>>,
+}
+
+impl Encryption {
+    /// Whether the PDF is encrypted.
+    pub(crate) const fn is_enabled(&self) -> bool {
+        self.user_password.is_some() || self.owner_password.is_some() || self.permissions.is_some()
+    }
+
+    /// Fills in the settings missing from these with the ones from `other`.
+    pub(crate) fn or(self, other: Self) -> Self {
+        Encryption {
+            user_password: self.user_password.or(other.user_password),
+            owner_password: self.owner_password.or(other.owner_password),
+            permissions: self.permissions.or(other.permissions),
+        }
+    }
+}
+
+impl Display for Encryption {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        // The passwords themselves are never shown
+        let permissions: Vec<String> = self
+            .permissions
+            .iter()
+            .flatten()
+            .map(ToString::to_string)
+            .collect();
+        write!(
+            f,
+            "(user password: {}, owner password: {}, allow: {})",
+            self.user_password.is_some(),
+            self.owner_password.is_some(),
+            permissions.join(" ")
+        )
+    }
+}
+
+/// An area that opens a link when clicked.
+pub(crate) struct Link {
+    pub(crate) position: Point,
+    pub(crate) size: Size,
+    /// A URI, or `#` followed by a page number to link within the document.
+    pub(crate) target: String,
+}
+
+impl Display for Link {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "({}, {}, {})", self.position, self.size, self.target)
+    }
+}
+
 /// The postion & size of the `photo` in the YAML file.
 pub(crate) struct Photo {
     pub(crate) position: Point,
     pub(crate) size: Size,
+    pub(crate) fit: Option<Fit>,
+    /// The text drawn in place of a missing photo, with `\n` between lines.
+    pub(crate) placeholder: Option<String>,
+    /// The frame drawn around a missing photo.
+    pub(crate) line_options: LineOptions,
+    /// The font of the placeholder text.
+    pub(crate) font_options: FontOptions,
 }
 
 impl Display for Photo {
     fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
-        write!(f, "({}, {})", self.position, self.size,)
+        write!(
+            f,
+            "({}, {}, {}, {}, {})",
+            self.position,
+            self.size,
+            self.fit.unwrap_or_default(),
+            self.placeholder
+                .as_deref()
+                .unwrap_or(DEFAULT_PHOTO_PLACEHOLDER),
+            self.line_options
+        )
+    }
+}
+
+/// An image, such as a logo, signature or seal, drawn from a file.
+pub(crate) struct Image {
+    pub(crate) position: Point,
+    pub(crate) size: Size,
+    /// The path to the image, or a variable holding it.
+    pub(crate) path: String,
+    pub(crate) fit: Option<Fit>,
+    /// How opaque to draw the image, from 0 to 1.
+    pub(crate) opacity: Option<f64>,
+    /// The page to draw, for PDF files.
+    pub(crate) page: Option<u32>,
+}
+
+impl Display for Image {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(
+            f,
+            "({}, {}, {}, {}, {}, {})",
+            self.position,
+            self.size,
+            self.path,
+            self.fit.unwrap_or_default(),
+            self.opacity.unwrap_or(1.0_f64),
+            self.page.unwrap_or(1)
+        )
+    }
+}
+
+/// A QR code encoding a value, drawn as a square.
+pub(crate) struct QrCode {
+    pub(crate) position: Point,
+    /// The length of the sides, including the quiet zone.
+    pub(crate) size: Mm,
+    pub(crate) value: String,
+    pub(crate) error_correction: Option<ErrorCorrection>,
+    /// The number of blank modules around the code.
+    pub(crate) quiet_zone: Option<u32>,
+}
+
+impl Display for QrCode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(
+            f,
+            "({}, {}, {}, {}, {})",
+            self.position,
+            self.size.0,
+            self.value,
+            self.error_correction.unwrap_or_default(),
+            self.quiet_zone.unwrap_or(DEFAULT_QUIET_ZONE)
+        )
     }
 }
 
@@ -74,6 +260,11 @@ pub(crate) struct TextBox {
     pub(crate) size: Size,
     pub(crate) value: String,
     pub(crate) font_options: FontOptions,
+    pub(crate) overflow: Option<Overflow>,
+    /// Where clicking the text links to.
+    pub(crate) link: Option<String>,
+    /// The line in the style file the text box was defined on.
+    pub(crate) line_number: usize,
 }
 
 impl Display for TextBox {
@@ -130,6 +321,9 @@ pub(crate) struct MiscBox {
     pub(crate) height: Mm,
     pub(crate) value: String,
     pub(crate) font_options: FontOptions,
+    pub(crate) overflow: Option<Overflow>,
+    /// The line in the style file the box was defined on.
+    pub(crate) line_number: usize,
 }
 
 impl Display for MiscBox {
@@ -208,7 +402,7 @@ impl Display for Lines {
     fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
         let mut position_text: String = String::new();
         for point in &self.positions {
-            let point_text = format!("{}, ", point);
+            let point_text = format!("{point}, ");
             position_text.push_str(&point_text);
         }
         write!(
@@ -12,6 +12,14 @@ use std::str::FromStr;
 
 pub(crate) const DEFAULT_FONT_FACE: &str = "mincho";
 pub(crate) const DEFAULT_FONT_SIZE: f64 = 12.0_f64;
+/// The distance between lines of text, relative to the font size.
+pub(crate) const DEFAULT_LINE_HEIGHT: f64 = 1.0_f64;
+/// The instructions printed in place of a missing photo, as on standard rirekisho forms.
+pub(crate) const DEFAULT_PHOTO_PLACEHOLDER: &str = "写真をはる位置\\n縦 36〜40mm\\n横 24〜30mm";
+/// The font size of the instructions printed in place of a missing photo.
+pub(crate) const DEFAULT_PLACEHOLDER_FONT_SIZE: f64 = 8.0_f64;
+/// The blank modules around a QR code, as recommended by the standard.
+pub(crate) const DEFAULT_QUIET_ZONE: u32 = 4;
 pub(crate) const DEFAULT_LINE_WIDTH: f32 = 0.5;
 
 // Represents a position in 2D space.
@@ -125,11 +133,306 @@ impl FromStr for LineStyle {
     }
 }
 
+/// How text that does not fit inside its box is handled.
+#[derive(Copy, Clone, PartialEq)]
+pub(crate) enum Overflow {
+    /// Wrap the text to the box & reduce the font size until it fits.
+    Shrink,
+    /// Hide the text outside of the box.
+    Clip,
+    /// Stop rendering with an error.
+    Error,
+    /// Print a warning & draw the text anyway.
+    Warn,
+}
+
+impl Display for Overflow {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match *self {
+            Overflow::Shrink => write!(f, "shrink"),
+            Overflow::Clip => write!(f, "clip"),
+            Overflow::Error => write!(f, "error"),
+            Overflow::Warn => write!(f, "warn"),
+        }
+    }
+}
+
+impl FromStr for Overflow {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "shrink" => Ok(Overflow::Shrink),
+            "clip" => Ok(Overflow::Clip),
+            "error" => Ok(Overflow::Error),
+            "warn" => Ok(Overflow::Warn),
+            _ => Err(anyhow!("Failed to convert to Overflow from string")),
+        }
+    }
+}
+
+/// The direction text is written in.
+#[derive(Copy, Clone, PartialEq, Default)]
+pub(crate) enum Direction {
+    /// Lines run left to right & are stacked from top to bottom.
+    #[default]
+    Horizontal,
+    /// Lines run top to bottom & are stacked from right to left (tategaki).
+    Vertical,
+}
+
+impl Display for Direction {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match *self {
+            Direction::Horizontal => write!(f, "horizontal"),
+            Direction::Vertical => write!(f, "vertical"),
+        }
+    }
+}
+
+impl FromStr for Direction {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "horizontal" => Ok(Direction::Horizontal),
+            "vertical" => Ok(Direction::Vertical),
+            _ => Err(anyhow!("Failed to convert to Direction from string")),
+        }
+    }
+}
+
+/// How an image is fitted into its frame.
+#[derive(Copy, Clone, PartialEq, Default)]
+pub(crate) enum Fit {
+    /// Scale the image to fit inside the frame, keeping its aspect ratio.
+    Contain,
+    /// Crop the image around its center to the frame's aspect ratio & fill the frame.
+    #[default]
+    Cover,
+    /// Stretch the image to fill the frame.
+    Stretch,
+}
+
+impl Display for Fit {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match *self {
+            Fit::Contain => write!(f, "contain"),
+            Fit::Cover => write!(f, "cover"),
+            Fit::Stretch => write!(f, "stretch"),
+        }
+    }
+}
+
+impl FromStr for Fit {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "contain" => Ok(Fit::Contain),
+            "cover" => Ok(Fit::Cover),
+            "stretch" => Ok(Fit::Stretch),
+            _ => Err(anyhow!("Failed to convert to Fit from string")),
+        }
+    }
+}
+
+/// How much of a QR code can be damaged while staying readable.
+#[derive(Copy, Clone, Default)]
+pub(crate) enum ErrorCorrection {
+    /// About 7% can be restored.
+    Low,
+    /// About 15% can be restored.
+    #[default]
+    Medium,
+    /// About 25% can be restored.
+    Quartile,
+    /// About 30% can be restored.
+    High,
+}
+
+impl Display for ErrorCorrection {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match *self {
+            ErrorCorrection::Low => write!(f, "L"),
+            ErrorCorrection::Medium => write!(f, "M"),
+            ErrorCorrection::Quartile => write!(f, "Q"),
+            ErrorCorrection::High => write!(f, "H"),
+        }
+    }
+}
+
+impl FromStr for ErrorCorrection {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "L" => Ok(ErrorCorrection::Low),
+            "M" => Ok(ErrorCorrection::Medium),
+            "Q" => Ok(ErrorCorrection::Quartile),
+            "H" => Ok(ErrorCorrection::High),
+            _ => Err(anyhow!("Failed to convert to ErrorCorrection from string")),
+        }
+    }
+}
+
+/// How thick the strokes of a font are.
+#[derive(Copy, Clone, PartialEq, Default)]
+pub(crate) enum FontWeight {
+    #[default]
+    Normal,
+    Bold,
+}
+
+impl Display for FontWeight {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match *self {
+            FontWeight::Normal => write!(f, "normal"),
+            FontWeight::Bold => write!(f, "bold"),
+        }
+    }
+}
+
+impl FromStr for FontWeight {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "normal" => Ok(FontWeight::Normal),
+            "bold" => Ok(FontWeight::Bold),
+            _ => Err(anyhow!("Failed to convert to FontWeight from string")),
+        }
+    }
+}
+
+/// Whether a font is upright or slanted.
+#[derive(Copy, Clone, PartialEq, Default)]
+pub(crate) enum FontStyle {
+    #[default]
+    Normal,
+    Italic,
+}
+
+impl Display for FontStyle {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match *self {
+            FontStyle::Normal => write!(f, "normal"),
+            FontStyle::Italic => write!(f, "italic"),
+        }
+    }
+}
+
+impl FromStr for FontStyle {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "normal" => Ok(FontStyle::Normal),
+            "italic" => Ok(FontStyle::Italic),
+            _ => Err(anyhow!("Failed to convert to FontStyle from string")),
+        }
+    }
+}
+
+/// How lines of text are placed across the width they are aligned in.
+#[derive(Copy, Clone, PartialEq, Default)]
+pub(crate) enum Align {
+    #[default]
+    Left,
+    Center,
+    Right,
+    /// Spreads the characters evenly across the width (均等割付), for labels such as 氏名.
+    Distribute,
+}
+
+impl Display for Align {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match *self {
+            Align::Left => write!(f, "left"),
+            Align::Center => write!(f, "center"),
+            Align::Right => write!(f, "right"),
+            Align::Distribute => write!(f, "distribute"),
+        }
+    }
+}
+
+impl FromStr for Align {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "left" => Ok(Align::Left),
+            "center" => Ok(Align::Center),
+            "right" => Ok(Align::Right),
+            "distribute" => Ok(Align::Distribute),
+            _ => Err(anyhow!("Failed to convert to Align from string")),
+        }
+    }
+}
+
+/// What readers of an encrypted PDF may do without the owner password.
+#[derive(Copy, Clone, PartialEq)]
+pub(crate) enum Permission {
+    /// Printing, at full quality.
+    Print,
+    /// Copying text & images out of the document.
+    Copy,
+    /// Changing the contents of the pages.
+    Modify,
+    /// Adding comments & filling in forms.
+    Annotate,
+    /// Inserting, rotating & removing pages.
+    Assemble,
+}
+
+impl Display for Permission {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match *self {
+            Permission::Print => write!(f, "print"),
+            Permission::Copy => write!(f, "copy"),
+            Permission::Modify => write!(f, "modify"),
+            Permission::Annotate => write!(f, "annotate"),
+            Permission::Assemble => write!(f, "assemble"),
+        }
+    }
+}
+
+impl FromStr for Permission {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "print" => Ok(Permission::Print),
+            "copy" => Ok(Permission::Copy),
+            "modify" => Ok(Permission::Modify),
+            "annotate" => Ok(Permission::Annotate),
+            "assemble" => Ok(Permission::Assemble),
+            _ => Err(anyhow!("Failed to convert to Permission from string")),
+        }
+    }
+}
+
 // The options to customize the font.
 #[derive(Clone)]
 pub(crate) struct FontOptions {
     pub(crate) font_size: Option<f64>,
     pub(crate) font_face: Option<String>,
+    pub(crate) direction: Option<Direction>,
+    pub(crate) font_weight: Option<FontWeight>,
+    pub(crate) font_style: Option<FontStyle>,
+    /// Whether to thicken the strokes of fonts that have no bold variant.
+    pub(crate) synthetic_bold: Option<bool>,
+    pub(crate) align: Option<Align>,
+    /// The width lines are aligned in. Without it, centered & right aligned lines are placed
+    /// around & before their position.
+    pub(crate) width: Option<Mm>,
+    /// The extra space after each character.
+    pub(crate) letter_spacing: Option<Mm>,
+    /// The distance between lines, relative to the font size.
+    pub(crate) line_height: Option<f64>,
+    /// Whether to draw `**bold**`, `*italic*`, `` `code` `` & `[text](url)` markup in values,
+    /// instead of the characters as written.
+    pub(crate) markup: Option<bool>,
 }
 
 impl Default for FontOptions {
@@ -137,6 +440,15 @@ impl Default for FontOptions {
         FontOptions {
             font_size: Some(DEFAULT_FONT_SIZE),
             font_face: Some(DEFAULT_FONT_FACE.to_owned()),
+            direction: Some(Direction::default()),
+            font_weight: Some(FontWeight::default()),
+            font_style: Some(FontStyle::default()),
+            synthetic_bold: Some(false),
+            align: Some(Align::default()),
+            width: None,
+            letter_spacing: Some(Mm(0.0_f64)),
+            line_height: Some(DEFAULT_LINE_HEIGHT),
+            markup: Some(false),
         }
     }
 }
@@ -145,11 +457,21 @@ impl Display for FontOptions {
     fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
         write!(
             f,
-            "({}, {})",
+            "({}, {}, {}, {}, {}, {}, {}, {}, {}, {}, {})",
             self.font_size.unwrap_or(DEFAULT_FONT_SIZE),
-            (&self.font_face)
+            self.font_face
                 .clone()
                 .unwrap_or_else(|| DEFAULT_FONT_FACE.to_owned()),
+            self.direction.unwrap_or_default(),
+            self.font_weight.unwrap_or_default(),
+            self.font_style.unwrap_or_default(),
+            self.synthetic_bold.unwrap_or_default(),
+            self.align.unwrap_or_default(),
+            self.width
+                .map_or_else(|| "auto".to_owned(), |width| width.0.to_string()),
+            self.letter_spacing.unwrap_or(Mm(0.0_f64)).0,
+            self.line_height.unwrap_or(DEFAULT_LINE_HEIGHT),
+            self.markup.unwrap_or_default(),
         )
     }
 }
@@ -180,3 +502,40 @@ impl Display for LineOptions {
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_overflow() -> Result<()> {
+        for overflow in [
+            Overflow::Shrink,
+            Overflow::Clip,
+            Overflow::Error,
+            Overflow::Warn,
+        ] {
+            assert!(overflow.to_string().parse::<Overflow>()? == overflow);
+        }
+        assert!("squash".parse::<Overflow>().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn parse_fit() -> Result<()> {
+        for fit in [Fit::Contain, Fit::Cover, Fit::Stretch] {
+            assert!(fit.to_string().parse::<Fit>()? == fit);
+        }
+        assert!("Cover".parse::<Fit>().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn parse_align() -> Result<()> {
+        for align in [Align::Left, Align::Center, Align::Right, Align::Distribute] {
+            assert!(align.to_string().parse::<Align>()? == align);
+        }
+        assert!("justify".parse::<Align>().is_err());
+        Ok(())
+    }
+}
@@ -1,9 +1,11 @@
 //! Structs for handling the input YAML file,
 
 use serde::Deserialize;
-use std::path::PathBuf;
+use serde_yaml::Value;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 
-#[allow(dead_code)]
+#[allow(dead_code, reason = "every field is deserialized even if unused")]
 #[derive(Deserialize)]
 /// An entry in a table (such as the education table)
 pub(crate) struct Entry {
@@ -12,7 +14,7 @@ pub(crate) struct Entry {
     pub(crate) value: String,
 }
 
-#[allow(dead_code)]
+#[allow(dead_code, reason = "every field is deserialized even if unused")]
 #[derive(Deserialize)]
 /// The valid fields in the input YAML file.
 pub(crate) struct YAMLArgs {
@@ -23,6 +25,8 @@ pub(crate) struct YAMLArgs {
     pub(crate) gender: String,
     pub(crate) cell_phone: String,
     pub(crate) email: String,
+    /// The photo to paste, or empty to leave a place to paste one.
+    #[serde(default)]
     pub(crate) photo: PathBuf,
     pub(crate) address_kana: String,
     pub(crate) address: String,
@@ -52,4 +56,22 @@ pub(crate) struct YAMLArgs {
     pub(crate) hobby: String,
     pub(crate) motivation: String,
     pub(crate) request: String,
+    /// Any other fields, usable as variables in the style file.
+    #[serde(flatten)]
+    pub(crate) extra: HashMap<String, Value>,
+    /// The directory of the input file, which paths in it are relative to.
+    #[serde(skip)]
+    pub(crate) directory: PathBuf,
+}
+
+impl YAMLArgs {
+    /// Makes the file paths in the input relative to the directory of the input file.
+    pub(crate) fn resolve_paths(&mut self, input_path: &Path) {
+        if let Some(directory) = input_path.parent() {
+            if !self.photo.as_os_str().is_empty() {
+                self.photo = directory.join(&self.photo);
+            }
+            self.directory = directory.to_path_buf();
+        }
+    }
 }